@@ -0,0 +1,43 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use qoi::{decode_to_vec, encode_to_vec, Channels, Decoder, Encoder};
+
+fuzz_target!(|input: (u16, u16, bool, &[u8])| {
+    let (w, h, is_4, data) = input;
+    let channels = if is_4 { Channels::Rgba } else { Channels::Rgb };
+    let bpp = channels.bytes_per_pixel();
+    let w = 1 + w as usize % 260;
+    let h = (1 + h as usize % 260).min(data.len() / (w * bpp));
+    if h == 0 {
+        return;
+    }
+    let data = &data[..w * h * bpp];
+    let (w, h) = (w as u32, h as u32);
+
+    let encoded = encode_to_vec(data, w, h).unwrap();
+    let (header, decoded) = decode_to_vec(&encoded).unwrap();
+    assert_eq!((header.width, header.height, header.channels), (w, h, channels));
+    assert_eq!(decoded, data);
+
+    let encoder = Encoder::new(data, w, h).unwrap();
+    assert_eq!(encoder.encoded_len().unwrap(), encoded.len());
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+    let decoded = Decoder::from_stream(&encoded[..]).unwrap().decode_to_vec().unwrap();
+    assert_eq!(decoded, data);
+
+    // decoding into the other channel count must only drop or add an opaque alpha
+    let other = if is_4 { Channels::Rgb } else { Channels::Rgba };
+    let expected: Vec<u8> = data
+        .chunks_exact(bpp)
+        .flat_map(|px| {
+            let rgba = [px[0], px[1], px[2], px.get(3).copied().unwrap_or(0xff)];
+            rgba[..other.bytes_per_pixel()].to_vec()
+        })
+        .collect();
+    let decoded = Decoder::new(&encoded).unwrap().with_channels(other).decode_to_vec().unwrap();
+    assert_eq!(decoded, expected);
+    let decoded =
+        Decoder::from_stream(&encoded[..]).unwrap().with_channels(other).decode_to_vec().unwrap();
+    assert_eq!(decoded, expected);
+});
@@ -0,0 +1,251 @@
+use alloc::{borrow::Cow, vec, vec::Vec};
+
+use crate::consts::{QOI_HEADER_SIZE, QOI_PADDING_SIZE};
+use crate::decode::{decode_to_vec, walk_pixels, Decoder};
+use crate::encode::{Encoder, StreamEncoder};
+use crate::error::{Error, Result};
+use crate::header::Header;
+use crate::types::{Channels, ColorSpace, Rotation};
+use crate::utils::unlikely;
+
+/// Stacks two encoded images of the same width and number of channels vertically.
+///
+/// Both images are decoded into a single buffer (the top one first) which is then
+/// re-encoded; the color space of the top image is retained. Returns
+/// [`Error::IncompatibleImages`] if the widths or the numbers of channels differ.
+pub fn stack_vertical(top: &[u8], bottom: &[u8]) -> Result<Vec<u8>> {
+    let (mut top, mut bottom) = (Decoder::new(top)?, Decoder::new(bottom)?);
+    let (top_header, bottom_header) = (*top.header(), *bottom.header());
+    if top_header.width != bottom_header.width || top_header.channels != bottom_header.channels {
+        return Err(Error::IncompatibleImages { first: top_header, second: bottom_header });
+    }
+    let height = top_header.height + bottom_header.height; // can't overflow, both are <= 400M
+    let header =
+        Header::try_new(top_header.width, height, top_header.channels, top_header.colorspace)?;
+    let mut data = vec![0; top.required_buf_len() + bottom.required_buf_len()];
+    let (head, tail) = data.split_at_mut(top.required_buf_len());
+    top.decode_to_buf(head)?;
+    bottom.decode_to_buf(tail)?;
+    Encoder::new(&data, header.width, header.height)?
+        .with_colorspace(header.colorspace)
+        .encode_to_vec()
+}
+
+/// Concatenates two encoded images of the same height and number of channels horizontally.
+///
+/// Each image is decoded row by row directly into its half of the combined rows, which are
+/// then re-encoded; the color space of the left image is retained. Returns
+/// [`Error::IncompatibleImages`] if the heights or the numbers of channels differ.
+pub fn concat_horizontal(left: &[u8], right: &[u8]) -> Result<Vec<u8>> {
+    let (mut left, mut right) = (Decoder::new(left)?, Decoder::new(right)?);
+    let (left_header, right_header) = (*left.header(), *right.header());
+    if left_header.height != right_header.height || left_header.channels != right_header.channels {
+        return Err(Error::IncompatibleImages { first: left_header, second: right_header });
+    }
+    let width = left_header.width + right_header.width; // can't overflow, both are <= 400M
+    let header =
+        Header::try_new(width, left_header.height, left_header.channels, left_header.colorspace)?;
+    let channels = header.channels.bytes_per_pixel();
+    let (row_len, left_len) = (width as usize * channels, left_header.width as usize * channels);
+    let mut data = vec![0; header.n_pixels() * channels];
+    left.decode_for_each_row(|y, row| {
+        data[y * row_len..][..left_len].copy_from_slice(row);
+        Ok(())
+    })?;
+    right.decode_for_each_row(|y, row| {
+        data[y * row_len + left_len..][..row.len()].copy_from_slice(row);
+        Ok(())
+    })?;
+    Encoder::new(&data, header.width, header.height)?
+        .with_colorspace(header.colorspace)
+        .encode_to_vec()
+}
+
+/// Transcodes an encoded image into a 4-channel image with a constant alpha value.
+///
+/// The pixels are decoded and re-encoded one at a time in a single pass, so the image is
+/// never materialized in memory. The alpha channel of 4-channel images is replaced as well;
+/// the color space of the input is retained.
+pub fn transcode_add_alpha(input: &[u8], alpha: u8) -> Result<Vec<u8>> {
+    let header = Decoder::new(input)?.header().with_channels(Channels::Rgba);
+    let mut encoder = StreamEncoder::from_header(header);
+    let mut result = Ok(());
+    walk_pixels(input, |[r, g, b, _], count| {
+        for _ in 0..count {
+            if result.is_ok() {
+                result = encoder.push([r, g, b, alpha]);
+            }
+        }
+    })?;
+    result?;
+    encoder.finish()
+}
+
+/// Encodes an image as several concatenated images at halving resolutions, smallest first,
+/// so that a consumer can stop reading after any level.
+///
+/// The full image comes last, and each of the preceding images is box-filtered from the
+/// next one, with odd dimensions rounded up. At least one level is always emitted, and no
+/// levels smaller than 1x1 are. Individual levels can be decoded via
+/// [`decode_progressive_level`].
+pub fn encode_progressive(
+    data: &[u8], width: u32, height: u32, channels: Channels, levels: usize,
+) -> Result<Vec<u8>> {
+    let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+    if unlikely(data.len() != header.n_bytes()) {
+        return Err(Error::InvalidImageLength { size: data.len(), width, height });
+    }
+    let mut images = vec![(width, height, Cow::Borrowed(data))];
+    while let Some(&(width, height, ref data)) = images.last() {
+        if images.len() >= levels || (width, height) == (1, 1) {
+            break;
+        }
+        let half = box_halve(data, width as usize, height as usize, channels.bytes_per_pixel());
+        images.push(((width + 1) / 2, (height + 1) / 2, Cow::Owned(half)));
+    }
+    let mut out = Vec::new();
+    for (width, height, data) in images.iter().rev() {
+        out.extend(Encoder::new(data, *width, *height)?.encode_to_vec()?);
+    }
+    Ok(out)
+}
+
+/// Decodes the given level of an image produced by [`encode_progressive`], 0 being the
+/// smallest one.
+///
+/// Only the operations of the preceding levels are scanned to find where the requested
+/// level starts, so the input may be truncated right after it.
+pub fn decode_progressive_level(data: &[u8], level: usize) -> Result<(Header, Vec<u8>)> {
+    let mut offset = 0;
+    for _ in 0..level {
+        let (_, n_ops) = walk_pixels(&data[offset..], |_, _| ())?;
+        offset += QOI_HEADER_SIZE + n_ops + QOI_PADDING_SIZE;
+    }
+    decode_to_vec(&data[offset..])
+}
+
+/// Re-encodes an image as a banded container of independent images of `rows_per_band` rows
+/// each (the last one may be shorter), so that it can later be decoded in parallel.
+///
+/// The container is non-standard: it starts with the number of bands and the length of each
+/// band in bytes (all as big-endian `u32`), followed by the bands; it can be decoded via
+/// [`decode_banded`](crate::decode_banded). A band height of zero is treated as one.
+pub fn reencode_banded(bytes: &[u8], rows_per_band: u32) -> Result<Vec<u8>> {
+    let (header, data) = decode_to_vec(bytes)?;
+    let rows_per_band = rows_per_band.clamp(1, header.height);
+    let row_len = header.width as usize * header.channels.bytes_per_pixel();
+    let mut bands = Vec::new();
+    for band in data.chunks(rows_per_band as usize * row_len) {
+        #[allow(clippy::cast_possible_truncation)]
+        let height = (band.len() / row_len) as u32;
+        let encoder = Encoder::new(band, header.width, height)?.with_colorspace(header.colorspace);
+        bands.push(encoder.encode_to_vec()?);
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let mut out = (bands.len() as u32).to_be_bytes().to_vec();
+    for band in &bands {
+        #[allow(clippy::cast_possible_truncation)]
+        out.extend_from_slice(&(band.len() as u32).to_be_bytes());
+    }
+    out.extend(bands.concat());
+    Ok(out)
+}
+
+/// Rotates an encoded image clockwise by a multiple of 90 degrees and re-encodes it.
+///
+/// Width and height are swapped for [`Rotation::Deg90`] and [`Rotation::Deg270`]; the number
+/// of channels and the color space of the input are retained.
+pub fn rotate(bytes: &[u8], degrees: Rotation) -> Result<Vec<u8>> {
+    let (header, data) = decode_to_vec(bytes)?;
+    let (w, h) = (header.width as usize, header.height as usize);
+    let bpp = header.channels.bytes_per_pixel();
+    let (out_w, out_h) = if degrees.swaps_dimensions() { (h, w) } else { (w, h) };
+    let mut out = vec![0; data.len()];
+    for (i, px) in out.chunks_exact_mut(bpp).enumerate() {
+        let (x, y) = (i % out_w, i / out_w);
+        let (src_x, src_y) = match degrees {
+            Rotation::Deg90 => (y, h - 1 - x),
+            Rotation::Deg180 => (w - 1 - x, h - 1 - y),
+            Rotation::Deg270 => (w - 1 - y, x),
+        };
+        let src = (src_y * w + src_x) * bpp;
+        px.copy_from_slice(&data[src..src + bpp]);
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let encoder = Encoder::new(&out, out_w as u32, out_h as u32)?;
+    encoder.with_colorspace(header.colorspace).encode_to_vec()
+}
+
+/// Marker that replaces a frame identical to the previous one in a sequence.
+///
+/// Encoded images always start with the magic, so the marker can't be mistaken for one.
+const SEQUENCE_REPEAT: u8 = 0;
+
+/// Encodes a sequence of same-sized frames as concatenated images, replacing each frame that
+/// is byte-identical to the previous one with a single-byte marker.
+///
+/// The number of channels of each frame is inferred from its length, as in [`Encoder::new`].
+/// The sequence can be decoded via [`decode_sequence_dedup`].
+pub fn encode_sequence_dedup<T: AsRef<[u8]>>(
+    frames: &[T], width: u32, height: u32,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut prev: Option<&[u8]> = None;
+    for frame in frames {
+        let frame = frame.as_ref();
+        if prev == Some(frame) {
+            out.push(SEQUENCE_REPEAT);
+        } else {
+            out.extend(Encoder::new(frame, width, height)?.encode_to_vec()?);
+            prev = Some(frame);
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes all frames of a sequence produced by [`encode_sequence_dedup`].
+///
+/// Repeated frames are decoded once and then copied; a repeat marker in place of the first
+/// frame is rejected the same way as any other invalid image.
+pub fn decode_sequence_dedup(data: &[u8]) -> Result<Vec<(Header, Vec<u8>)>> {
+    let mut frames: Vec<(Header, Vec<u8>)> = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        if data[offset] == SEQUENCE_REPEAT {
+            if let Some(prev) = frames.last().cloned() {
+                frames.push(prev);
+                offset += 1;
+                continue;
+            }
+        }
+        let (_, n_ops) = walk_pixels(&data[offset..], |_, _| ())?;
+        let len = QOI_HEADER_SIZE + n_ops + QOI_PADDING_SIZE;
+        let frame = data.get(offset..offset + len).ok_or(Error::UnexpectedBufferEnd)?;
+        frames.push(decode_to_vec(frame)?);
+        offset += len;
+    }
+    Ok(frames)
+}
+
+/// Halves the dimensions of an image (rounding up) by averaging blocks of 2x2 pixels.
+#[allow(clippy::cast_possible_truncation)]
+fn box_halve(data: &[u8], width: usize, height: usize, channels: usize) -> Vec<u8> {
+    let (half_width, half_height) = ((width + 1) / 2, (height + 1) / 2);
+    let mut out = Vec::with_capacity(half_width * half_height * channels);
+    for y in 0..half_height {
+        let rows = 2 * y..(2 * y + 2).min(height);
+        for x in 0..half_width {
+            let cols = 2 * x..(2 * x + 2).min(width);
+            let n = rows.len() * cols.len();
+            for c in 0..channels {
+                let sum: usize = rows
+                    .clone()
+                    .flat_map(|y| cols.clone().map(move |x| data[(y * width + x) * channels + c]))
+                    .map(usize::from)
+                    .sum();
+                out.push(((sum + n / 2) / n) as u8);
+            }
+        }
+    }
+    out
+}
@@ -11,7 +11,9 @@ pub const QOI_HEADER_SIZE: usize = 14;
 
 pub const QOI_PADDING: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 0x01]; // 7 zeros and one 0x01 marker
 pub const QOI_PADDING_SIZE: usize = 8;
+pub const QOI_TRAILER_MAX_LEN: usize = u8::MAX as usize; // the trailer is prefixed with a length byte
 
 pub const QOI_MAGIC: u32 = u32::from_be_bytes(*b"qoif");
+pub const QOI_PREWARM_MAGIC: u32 = u32::from_be_bytes(*b"qoip"); // non-standard, pre-seeded index
 
 pub const QOI_PIXELS_MAX: usize = 400_000_000;
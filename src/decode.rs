@@ -1,7 +1,15 @@
 #[cfg(any(feature = "std", feature = "alloc"))]
 use alloc::{vec, vec::Vec};
+use core::convert::{Infallible, TryFrom};
+use core::fmt::{self, Debug};
+use core::iter;
+#[cfg(feature = "uninit")]
+use core::mem::MaybeUninit;
+use core::ops::ControlFlow;
+#[cfg(any(feature = "std", feature = "alloc"))]
+use core::ops::Range;
 #[cfg(feature = "std")]
-use std::io::Read;
+use std::{io::Read, path::Path};
 
 // TODO: can be removed once https://github.com/rust-lang/rust/issues/74985 is stable
 use bytemuck::{cast_slice_mut, Pod};
@@ -10,10 +18,12 @@ use crate::consts::{
     QOI_HEADER_SIZE, QOI_OP_DIFF, QOI_OP_INDEX, QOI_OP_LUMA, QOI_OP_RGB, QOI_OP_RGBA, QOI_OP_RUN,
     QOI_PADDING, QOI_PADDING_SIZE,
 };
+#[cfg(any(feature = "std", feature = "alloc"))]
+use crate::consts::{QOI_MAGIC, QOI_PREWARM_MAGIC};
 use crate::error::{Error, Result};
 use crate::header::Header;
-use crate::pixel::{Pixel, SupportedChannels};
-use crate::types::Channels;
+use crate::pixel::{ColorHash, Pixel, StandardHash, SupportedChannels};
+use crate::types::{Channels, ColorSpace, Component, Endianness};
 use crate::utils::{cold, unlikely};
 
 const QOI_OP_INDEX_END: u8 = QOI_OP_INDEX | 0x3f;
@@ -21,8 +31,40 @@ const QOI_OP_RUN_END: u8 = QOI_OP_RUN | 0x3d; // <- note, 0x3d (not 0x3f)
 const QOI_OP_DIFF_END: u8 = QOI_OP_DIFF | 0x3f;
 const QOI_OP_LUMA_END: u8 = QOI_OP_LUMA | 0x3f;
 
+/// Decoding state that persists between calls, so that an image can be decoded in steps.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct DecodeState {
+    index: [Pixel<4>; 256],
+    px: Pixel<4>,
+    run: usize,
+}
+
+impl DecodeState {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { index: [Pixel::new(); 256], px: Pixel::new().with_a(0xff), run: 0 }
+    }
+
+    /// Same as assigning a new state, but without a temporary copy on the stack.
+    #[inline]
+    fn reset(&mut self) {
+        self.index.fill(Pixel::new());
+        (self.px, self.run) = (Pixel::new().with_a(0xff), 0);
+    }
+}
+
+impl Default for DecodeState {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[inline]
-fn decode_impl_slice<const N: usize, const RGBA: bool>(data: &[u8], out: &mut [u8]) -> Result<usize>
+fn decode_impl_slice<H: ColorHash, const N: usize, const RGBA: bool>(
+    state: &mut DecodeState, data: &[u8], out: &mut [u8],
+) -> Result<usize>
 where
     Pixel<N>: SupportedChannels,
     [u8; N]: Pod,
@@ -31,16 +73,29 @@ where
     let data_len = data.len();
     let mut data = data;
 
-    let mut index = [Pixel::<4>::new(); 256];
-    let mut px = Pixel::<N>::new().with_a(0xff);
+    let index = &mut state.index;
+    let mut px = Pixel::<N>::new();
+    px.update(state.px);
+    // alpha is tracked separately so that the index stays in sync with the encoder when
+    // decoding an RGBA image into RGB output
+    let mut alpha = state.px.a_or(0xff);
     let mut px_rgba: Pixel<4>;
 
+    if state.run != 0 {
+        let run = state.run.min(pixels.len());
+        let (phead, ptail) = pixels.split_at_mut(run); // can't panic
+        phead.fill(px.into());
+        pixels = ptail;
+        state.run -= run;
+    }
+
     while let [px_out, ptail @ ..] = pixels {
         pixels = ptail;
         match data {
             [b1 @ QOI_OP_INDEX..=QOI_OP_INDEX_END, dtail @ ..] => {
                 px_rgba = index[*b1 as usize];
                 px.update(px_rgba);
+                alpha = px_rgba.a_or(0xff);
                 *px_out = px.into();
                 data = dtail;
                 continue;
@@ -51,14 +106,17 @@ where
             }
             [QOI_OP_RGBA, r, g, b, a, dtail @ ..] if RGBA => {
                 px.update_rgba(*r, *g, *b, *a);
+                alpha = *a;
                 data = dtail;
             }
             [b1 @ QOI_OP_RUN..=QOI_OP_RUN_END, dtail @ ..] => {
                 *px_out = px.into();
-                let run = ((b1 & 0x3f) as usize).min(pixels.len());
-                let (phead, ptail) = pixels.split_at_mut(run); // can't panic
+                let run = (b1 & 0x3f) as usize;
+                let run_now = run.min(pixels.len());
+                let (phead, ptail) = pixels.split_at_mut(run_now); // can't panic
                 phead.fill(px.into());
                 pixels = ptail;
+                state.run = run - run_now;
                 data = dtail;
                 continue;
             }
@@ -78,29 +136,119 @@ where
             }
         }
 
-        px_rgba = px.as_rgba(0xff);
-        index[px_rgba.hash_index() as usize] = px_rgba;
+        px_rgba = px.as_rgba(alpha);
+        index[(H::hash_index(px_rgba.into()) & 0x3f) as usize] = px_rgba;
         *px_out = px.into();
     }
 
+    state.px = px.as_rgba(alpha);
+    Ok(data_len - data.len())
+}
+
+#[inline]
+fn decode_padding_slice(data: &[u8]) -> Result<()> {
     if unlikely(data.len() < QOI_PADDING_SIZE) {
-        return Err(Error::UnexpectedBufferEnd);
+        Err(Error::UnexpectedBufferEnd)
     } else if unlikely(data[..QOI_PADDING_SIZE] != QOI_PADDING) {
-        return Err(Error::InvalidPadding);
+        Err(Error::InvalidPadding)
+    } else {
+        Ok(())
     }
+}
 
-    Ok(data_len.saturating_sub(data.len()).saturating_sub(QOI_PADDING_SIZE))
+/// Walks the operations of an encoded image without materializing it, calling `visit` with
+/// each reconstructed RGBA pixel and the number of consecutive pixels it covers.
+///
+/// Returns the header and the length of the operations stream in bytes.
+pub fn walk_pixels(data: &[u8], mut visit: impl FnMut([u8; 4], usize)) -> Result<(Header, usize)> {
+    let res = try_walk_pixels(data, |px, count| {
+        visit(px, count);
+        ControlFlow::<Infallible>::Continue(())
+    })?;
+    match res {
+        ControlFlow::Continue(res) => Ok(res),
+        ControlFlow::Break(never) => match never {},
+    }
+}
+
+/// Same as [`walk_pixels`], but stops as soon as `visit` returns [`ControlFlow::Break`], in
+/// which case the rest of the image is not validated.
+pub fn try_walk_pixels<B>(
+    data: &[u8], mut visit: impl FnMut([u8; 4], usize) -> ControlFlow<B>,
+) -> Result<ControlFlow<B, (Header, usize)>> {
+    let header = Header::decode(data)?;
+    let mut data = &data[QOI_HEADER_SIZE..]; // can't panic
+    let data_len = data.len();
+    let mut index = [Pixel::<4>::new(); 256];
+    let mut px = Pixel::<4>::new().with_a(0xff);
+    let mut remaining = header.n_pixels();
+    while remaining != 0 {
+        let count = match data {
+            [b1 @ QOI_OP_INDEX..=QOI_OP_INDEX_END, dtail @ ..] => {
+                px = index[*b1 as usize];
+                data = dtail;
+                if let ControlFlow::Break(b) = visit(px.into(), 1) {
+                    return Ok(ControlFlow::Break(b));
+                }
+                remaining -= 1;
+                continue;
+            }
+            [QOI_OP_RGB, r, g, b, dtail @ ..] => {
+                px.update_rgb(*r, *g, *b);
+                data = dtail;
+                1
+            }
+            [QOI_OP_RGBA, r, g, b, a, dtail @ ..] => {
+                px.update_rgba(*r, *g, *b, *a);
+                data = dtail;
+                1
+            }
+            [b1 @ QOI_OP_RUN..=QOI_OP_RUN_END, dtail @ ..] => {
+                let run = (b1 & 0x3f) as usize + 1;
+                if unlikely(run > remaining) {
+                    return Err(Error::RunOverflow);
+                }
+                data = dtail;
+                if let ControlFlow::Break(b) = visit(px.into(), run) {
+                    return Ok(ControlFlow::Break(b));
+                }
+                remaining -= run;
+                continue;
+            }
+            [b1 @ QOI_OP_DIFF..=QOI_OP_DIFF_END, dtail @ ..] => {
+                px.update_diff(*b1);
+                data = dtail;
+                1
+            }
+            [b1 @ QOI_OP_LUMA..=QOI_OP_LUMA_END, b2, dtail @ ..] => {
+                px.update_luma(*b1, *b2);
+                data = dtail;
+                1
+            }
+            _ => {
+                cold();
+                return Err(Error::UnexpectedBufferEnd);
+            }
+        };
+        index[px.hash_index() as usize] = px;
+        if let ControlFlow::Break(b) = visit(px.into(), count) {
+            return Ok(ControlFlow::Break(b));
+        }
+        remaining -= count;
+    }
+    decode_padding_slice(data)?;
+    Ok(ControlFlow::Continue((header, data_len - data.len())))
 }
 
 #[inline]
-fn decode_impl_slice_all(
-    data: &[u8], out: &mut [u8], channels: u8, src_channels: u8,
+fn decode_impl_slice_all<H: ColorHash>(
+    state: &mut DecodeState, data: &[u8], out: &mut [u8], channels: u8, src_channels: u8,
 ) -> Result<usize> {
     match (channels, src_channels) {
-        (3, 3) => decode_impl_slice::<3, false>(data, out),
-        (3, 4) => decode_impl_slice::<3, true>(data, out),
-        (4, 3) => decode_impl_slice::<4, false>(data, out),
-        (4, 4) => decode_impl_slice::<4, true>(data, out),
+        (3, 3) => decode_impl_slice::<H, 3, false>(state, data, out),
+        (3, 4) => decode_impl_slice::<H, 3, true>(state, data, out),
+        (4, 3) => decode_impl_slice::<H, 4, false>(state, data, out),
+        (4, 4) => decode_impl_slice::<H, 4, true>(state, data, out),
         _ => {
             cold();
             Err(Error::InvalidChannels { channels })
@@ -119,6 +267,54 @@ pub fn decode_to_buf(buf: impl AsMut<[u8]>, data: impl AsRef<[u8]>) -> Result<He
     Ok(*decoder.header())
 }
 
+/// Working memory of the decoder (mainly the color index), allocated by the caller.
+///
+/// This is meant for targets with small stacks: [`decode_with_context`] keeps its state here
+/// instead of on the stack, so a context can be allocated once on the heap or in a `static`
+/// and reused. The context is reset at the start of every decode.
+#[derive(Clone, Default)]
+pub struct DecodeContext {
+    state: DecodeState,
+}
+
+impl DecodeContext {
+    /// Creates a new context; this is a const fn, so it can be used to initialize statics.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { state: DecodeState::new() }
+    }
+}
+
+/// Decode the image into a pre-allocated buffer, using the given context as working memory.
+///
+/// This is equivalent to [`decode_to_buf`], but keeps the decoder state in `ctx` rather than
+/// on the stack. The buffer must be able to hold the whole image, with the number of
+/// channels matching the header.
+pub fn decode_with_context(
+    ctx: &mut DecodeContext, mut buf: impl AsMut<[u8]>, data: impl AsRef<[u8]>,
+) -> Result<Header> {
+    let (buf, data) = (buf.as_mut(), data.as_ref());
+    let header = Header::decode(data)?;
+    let size = header.n_bytes();
+    if unlikely(buf.len() < size) {
+        return Err(Error::OutputBufferTooSmall { size: buf.len(), required: size });
+    }
+    ctx.state.reset();
+    let (ops, channels) = (&data[QOI_HEADER_SIZE..], header.channels.as_u8()); // can't panic
+    let n_read = decode_impl_slice_all::<StandardHash>(
+        &mut ctx.state,
+        ops,
+        &mut buf[..size],
+        channels,
+        channels,
+    )?;
+    if unlikely(ctx.state.run != 0) {
+        return Err(Error::RunOverflow);
+    }
+    decode_padding_slice(&ops[n_read..])?;
+    Ok(header)
+}
+
 /// Decode the image into a newly allocated vector.
 ///
 /// Note: the resulting number of channels will match the header. In order to change
@@ -131,6 +327,239 @@ pub fn decode_to_vec(data: impl AsRef<[u8]>) -> Result<(Header, Vec<u8>)> {
     Ok((*decoder.header(), out))
 }
 
+/// Decode a single row of the image into a newly allocated vector.
+///
+/// All of the preceding rows have to be decoded as well to reconstruct the decoder state,
+/// but only one row is kept in memory at a time; the rest of the image (including the
+/// padding) is not validated.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub fn decode_scanline(bytes: &[u8], row: usize) -> Result<Vec<u8>> {
+    let mut decoder = Decoder::new(bytes)?;
+    let (width, height) = (decoder.header.width as usize, decoder.header.height);
+    if unlikely(row >= height as usize) {
+        return Err(Error::RowOutOfBounds { row, height });
+    }
+    let channels = decoder.channels.as_u8();
+    let mut out = vec![0; width * channels as usize];
+    for _ in 0..=row {
+        decoder.decode_pixels(&mut out, channels)?;
+    }
+    Ok(out)
+}
+
+/// Decode an image encoded with a custom color hash into a newly allocated vector.
+///
+/// The magic bytes must match those of the hash, see
+/// [`Encoder::encode_to_vec_with_hash`](crate::Encoder::encode_to_vec_with_hash); with
+/// [`StandardHash`](crate::StandardHash), this is the same as [`decode_to_vec`].
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub fn decode_to_vec_with_hash<H: ColorHash>(data: impl AsRef<[u8]>) -> Result<(Header, Vec<u8>)> {
+    let data = data.as_ref();
+    if unlikely(data.len() < QOI_HEADER_SIZE) {
+        return Err(Error::UnexpectedBufferEnd);
+    }
+    let (head, ops) = data.split_at(QOI_HEADER_SIZE); // can't panic
+    let magic = u32::from_be_bytes([head[0], head[1], head[2], head[3]]);
+    if unlikely(magic != H::MAGIC) {
+        return Err(Error::InvalidMagic { magic });
+    }
+    let mut head =
+        <[u8; QOI_HEADER_SIZE]>::try_from(head).map_err(|_| Error::UnexpectedBufferEnd)?;
+    head[..4].copy_from_slice(&StandardHash::MAGIC.to_be_bytes());
+    let header = Header::decode(head)?;
+    let channels = header.channels.as_u8();
+    let mut out = vec![0; header.n_bytes()];
+    let n_read =
+        decode_impl_slice_all::<H>(&mut DecodeState::new(), ops, &mut out, channels, channels)?;
+    decode_padding_slice(&ops[n_read..])?;
+    Ok((header, out))
+}
+
+/// Decode an image encoded with a pre-seeded color index into a newly allocated vector.
+///
+/// See [`Encoder::encode_to_vec_prewarmed`](crate::Encoder::encode_to_vec_prewarmed); the
+/// magic bytes must be `qoip`, and the header is followed by the number of seeded colors
+/// and the colors themselves (with as many channels as the image), which are put into the
+/// index before decoding.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub fn decode_to_vec_prewarmed(data: impl AsRef<[u8]>) -> Result<(Header, Vec<u8>)> {
+    let data = data.as_ref();
+    if unlikely(data.len() <= QOI_HEADER_SIZE) {
+        return Err(Error::UnexpectedBufferEnd);
+    }
+    let (head, tail) = data.split_at(QOI_HEADER_SIZE); // can't panic
+    let magic = u32::from_be_bytes([head[0], head[1], head[2], head[3]]);
+    if unlikely(magic != QOI_PREWARM_MAGIC) {
+        return Err(Error::InvalidMagic { magic });
+    }
+    let mut head =
+        <[u8; QOI_HEADER_SIZE]>::try_from(head).map_err(|_| Error::UnexpectedBufferEnd)?;
+    head[..4].copy_from_slice(&QOI_MAGIC.to_be_bytes());
+    let header = Header::decode(head)?;
+    let channels = header.channels.as_u8();
+    let seeds_len = tail[0] as usize * channels as usize;
+    let seeds = tail.get(1..1 + seeds_len).ok_or(Error::UnexpectedBufferEnd)?;
+    let mut state = DecodeState::new();
+    for seed in seeds.chunks_exact(channels as usize) {
+        let px = Pixel::from([seed[0], seed[1], seed[2], seed.get(3).map_or(0xff, |&a| a)]);
+        state.index[px.hash_index() as usize] = px;
+    }
+    let ops = &tail[1 + seeds_len..];
+    let mut out = vec![0; header.n_bytes()];
+    let n_read =
+        decode_impl_slice_all::<StandardHash>(&mut state, ops, &mut out, channels, channels)?;
+    decode_padding_slice(&ops[n_read..])?;
+    Ok((header, out))
+}
+
+/// Read and decode the image from a file into a newly allocated vector of bytes.
+#[cfg(feature = "std")]
+#[inline]
+pub fn decode_file(path: impl AsRef<Path>) -> Result<(Header, Vec<u8>)> {
+    decode_to_vec(std::fs::read(path)?)
+}
+
+/// Memory-map a file and decode the image from the mapping into a newly allocated vector.
+///
+/// Unlike [`decode_file`], the file contents are never copied into an intermediate buffer,
+/// which reduces the peak memory usage for large images.
+///
+/// Note: the file must not be modified by other processes while it's being decoded.
+#[cfg(feature = "memmap2")]
+#[inline]
+pub fn decode_mmap(path: impl AsRef<Path>) -> Result<(Header, Vec<u8>)> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: the mapping is read-only and only lives until the end of this function;
+    // concurrent modification of the underlying file is documented as unsupported.
+    #[allow(unsafe_code)]
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    decode_to_vec(&mmap[..])
+}
+
+/// Read the metadata block that follows the end padding of an encoded image, if any.
+///
+/// This is the counterpart of [`Encoder::with_trailer_metadata`](crate::Encoder::with_trailer_metadata);
+/// `None` is returned if the image can't be decoded or the trailing bytes after the padding
+/// don't form a valid length-prefixed block.
+#[inline]
+pub fn read_trailer_metadata(data: &[u8]) -> Option<&[u8]> {
+    let (_, n_ops) = walk_pixels(data, |_, _| ()).ok()?;
+    let tail = data.get(QOI_HEADER_SIZE + n_ops..)?;
+    match tail.split_at(tail.len().min(QOI_PADDING_SIZE)) {
+        (padding, [len, metadata @ ..])
+            if padding == QOI_PADDING && metadata.len() == *len as usize =>
+        {
+            Some(metadata)
+        }
+        _ => None,
+    }
+}
+
+/// Counts the operations in an encoded image without reconstructing any pixels.
+///
+/// Only the tag bytes are inspected, and the payload bytes of each operation are skipped;
+/// the end padding is validated but not counted.
+pub fn count_ops(bytes: &[u8]) -> Result<usize> {
+    let header = Header::decode(bytes)?;
+    let mut data = &bytes[QOI_HEADER_SIZE..]; // can't panic
+    let (mut n_ops, mut remaining) = (0, header.n_pixels());
+    while remaining != 0 {
+        let (len, count) = match data.first() {
+            Some(&b1) => op_size(b1),
+            None => return Err(Error::UnexpectedBufferEnd),
+        };
+        if unlikely(count > remaining) {
+            return Err(Error::RunOverflow);
+        }
+        data = data.get(len..).ok_or(Error::UnexpectedBufferEnd)?;
+        remaining -= count;
+        n_ops += 1;
+    }
+    decode_padding_slice(data)?;
+    Ok(n_ops)
+}
+
+/// Rewrites the header of an image whose dimensions are corrupted but whose op stream is
+/// intact, given the true width of the image.
+///
+/// The pixels are counted by walking the operations up to the end padding, which must
+/// terminate the input, and the height is recomputed from the pixel count. Returns
+/// [`Error::IndivisiblePixelCount`] if the pixel count is not a multiple of `known_width`.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub fn repair_dimensions(bytes: &[u8], known_width: u32) -> Result<Vec<u8>> {
+    // validate the rest of the header with placeholder dimensions
+    let mut out = bytes.to_vec();
+    if let Some(dims) = out.get_mut(4..12) {
+        dims.copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]);
+    }
+    let header = Header::decode(&out)?;
+    let ops = out[QOI_HEADER_SIZE..].strip_suffix(&QOI_PADDING).ok_or(Error::InvalidPadding)?;
+    let n_pixels = count_op_pixels(ops).ok_or(Error::UnexpectedBufferEnd)?;
+    if unlikely(known_width == 0 || n_pixels % known_width as usize != 0) {
+        return Err(Error::IndivisiblePixelCount { n_pixels, width: known_width });
+    }
+    let height = u32::try_from(n_pixels / known_width as usize).unwrap_or(u32::MAX);
+    let header = Header::try_new(known_width, height, header.channels, header.colorspace)?;
+    out[4..8].copy_from_slice(&header.width.to_be_bytes());
+    out[8..12].copy_from_slice(&header.height.to_be_bytes());
+    Ok(out)
+}
+
+/// Splits a banded container produced by [`reencode_banded`](crate::reencode_banded) into
+/// the byte ranges of its bands, and returns them along with the header of the full image
+/// and the number of rows per band.
+///
+/// All bands must have the same width and number of channels, and all but the last one the
+/// same height, which the last one must not exceed.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub fn decode_band_layout(data: &[u8]) -> Result<(Header, usize, Vec<Range<usize>>)> {
+    let read_u32 = |offset: usize| -> Result<usize> {
+        let bytes = data.get(offset..offset + 4).ok_or(Error::UnexpectedBufferEnd)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let n_bands = read_u32(0)?;
+    let mut start = 4_usize.saturating_add(n_bands.saturating_mul(4));
+    let mut ranges = Vec::new();
+    for i in 0..n_bands {
+        let end = start.saturating_add(read_u32(4 + 4 * i)?);
+        ranges.push(start..end);
+        start = end;
+    }
+    let header_of = |range: &Range<usize>| -> Result<Header> {
+        Header::decode(data.get(range.clone()).ok_or(Error::UnexpectedBufferEnd)?)
+    };
+    let first = header_of(ranges.first().ok_or(Error::UnexpectedBufferEnd)?)?;
+    let mut height = 0_u32;
+    for (i, range) in ranges.iter().enumerate() {
+        let band = header_of(range)?;
+        let is_last = i == n_bands - 1;
+        if unlikely(
+            band.width != first.width
+                || band.channels != first.channels
+                || (band.height != first.height && !is_last)
+                || band.height > first.height,
+        ) {
+            return Err(Error::IncompatibleImages { first, second: band });
+        }
+        height = height.saturating_add(band.height);
+    }
+    let header = Header::try_new(first.width, height, first.channels, first.colorspace)?;
+    Ok((header, first.height as usize, ranges))
+}
+
+/// Decodes a banded container produced by [`reencode_banded`](crate::reencode_banded) into
+/// a newly allocated vector, one band after another.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub fn decode_banded(data: &[u8]) -> Result<(Header, Vec<u8>)> {
+    let (header, rows_per_band, ranges) = decode_band_layout(data)?;
+    let mut out = vec![0; header.n_bytes()];
+    let band_len = rows_per_band * header.width as usize * header.channels.bytes_per_pixel();
+    for (band, range) in out.chunks_mut(band_len).zip(ranges) {
+        Decoder::new(&data[range])?.decode_to_buf(band)?;
+    }
+    Ok((header, out))
+}
+
 /// Decode the image header from a slice of bytes.
 #[inline]
 pub fn decode_header(data: impl AsRef<[u8]>) -> Result<Header> {
@@ -140,7 +569,7 @@ pub fn decode_header(data: impl AsRef<[u8]>) -> Result<Header> {
 #[cfg(feature = "std")]
 #[inline]
 fn decode_impl_stream<R: Read, const N: usize, const RGBA: bool>(
-    data: &mut R, out: &mut [u8],
+    state: &mut DecodeState, data: &mut R, out: &mut [u8],
 ) -> Result<()>
 where
     Pixel<N>: SupportedChannels,
@@ -148,8 +577,21 @@ where
 {
     let mut pixels = cast_slice_mut::<_, [u8; N]>(out);
 
-    let mut index = [Pixel::<N>::new(); 256];
-    let mut px = Pixel::<N>::new().with_a(0xff);
+    let index = &mut state.index;
+    let mut px = Pixel::<N>::new();
+    px.update(state.px);
+    // alpha is tracked separately so that the index stays in sync with the encoder when
+    // decoding an RGBA image into RGB output
+    let mut alpha = state.px.a_or(0xff);
+    let mut px_rgba: Pixel<4>;
+
+    if state.run != 0 {
+        let run = state.run.min(pixels.len());
+        let (phead, ptail) = pixels.split_at_mut(run); // can't panic
+        phead.fill(px.into());
+        pixels = ptail;
+        state.run -= run;
+    }
 
     while let [px_out, ptail @ ..] = pixels {
         pixels = ptail;
@@ -158,7 +600,9 @@ where
         let [b1] = p;
         match b1 {
             QOI_OP_INDEX..=QOI_OP_INDEX_END => {
-                px = index[b1 as usize];
+                px_rgba = index[b1 as usize];
+                px.update(px_rgba);
+                alpha = px_rgba.a_or(0xff);
                 *px_out = px.into();
                 continue;
             }
@@ -171,13 +615,16 @@ where
                 let mut p = [0; 4];
                 data.read_exact(&mut p)?;
                 px.update_rgba(p[0], p[1], p[2], p[3]);
+                alpha = p[3];
             }
             QOI_OP_RUN..=QOI_OP_RUN_END => {
                 *px_out = px.into();
-                let run = ((b1 & 0x3f) as usize).min(pixels.len());
-                let (phead, ptail) = pixels.split_at_mut(run); // can't panic
+                let run = (b1 & 0x3f) as usize;
+                let run_now = run.min(pixels.len());
+                let (phead, ptail) = pixels.split_at_mut(run_now); // can't panic
                 phead.fill(px.into());
                 pixels = ptail;
+                state.run = run - run_now;
                 continue;
             }
             QOI_OP_DIFF..=QOI_OP_DIFF_END => {
@@ -194,29 +641,82 @@ where
             }
         }
 
-        index[px.hash_index() as usize] = px;
+        px_rgba = px.as_rgba(alpha);
+        index[px_rgba.hash_index() as usize] = px_rgba;
         *px_out = px.into();
     }
 
+    state.px = px.as_rgba(alpha);
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+#[inline]
+fn decode_padding_stream<R: Read>(data: &mut R) -> core::result::Result<(), PaddingError> {
     let mut p = [0_u8; QOI_PADDING_SIZE];
-    data.read_exact(&mut p)?;
+    let n = read_up_to(data, &mut p)?;
     if unlikely(p != QOI_PADDING) {
-        return Err(Error::InvalidPadding);
+        return diagnose_padding_stream(data, p, n);
     }
-
     Ok(())
 }
 
+/// Figures out whether the op stream mismatches the header, given the (up to 8) bytes that
+/// were read where the padding was expected. Unlike with slices, the bytes can't be looked
+/// at in advance, so the extra ops (if any) are consumed along with the padding.
+#[cfg(feature = "std")]
+#[cold]
+fn diagnose_padding_stream<R: Read>(
+    data: &mut R, mut p: [u8; QOI_PADDING_SIZE], n: usize,
+) -> core::result::Result<(), PaddingError> {
+    if n < QOI_PADDING_SIZE {
+        // the padding consists of single-pixel ops, so a pixel shortage makes the decoder
+        // run into it; this can't be told apart from a missing padding if nothing is left
+        let err = std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into();
+        return Err(if n != 0 && p[..n] == QOI_PADDING[QOI_PADDING_SIZE - n..] {
+            PaddingError::Missing(QOI_PADDING_SIZE - n, err)
+        } else {
+            PaddingError::Invalid(err)
+        });
+    }
+    let mut n_extra = 0;
+    while p != QOI_PADDING {
+        let (len, count) = op_size(p[0]);
+        p.copy_within(len.., 0);
+        if read_up_to(data, &mut p[QOI_PADDING_SIZE - len..])? != len {
+            return Err(PaddingError::Invalid(Error::InvalidPadding));
+        }
+        n_extra += count;
+    }
+    Err(PaddingError::Extra(n_extra))
+}
+
+/// Reads as many bytes as possible into the buffer, stopping early only at the end of the
+/// stream. Returns the number of bytes read.
+#[cfg(feature = "std")]
+fn read_up_to<R: Read>(data: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match data.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(n)
+}
+
 #[cfg(feature = "std")]
 #[inline]
 fn decode_impl_stream_all<R: Read>(
-    data: &mut R, out: &mut [u8], channels: u8, src_channels: u8,
+    state: &mut DecodeState, data: &mut R, out: &mut [u8], channels: u8, src_channels: u8,
 ) -> Result<()> {
     match (channels, src_channels) {
-        (3, 3) => decode_impl_stream::<_, 3, false>(data, out),
-        (3, 4) => decode_impl_stream::<_, 3, true>(data, out),
-        (4, 3) => decode_impl_stream::<_, 4, false>(data, out),
-        (4, 4) => decode_impl_stream::<_, 4, true>(data, out),
+        (3, 3) => decode_impl_stream::<_, 3, false>(state, data, out),
+        (3, 4) => decode_impl_stream::<_, 3, true>(state, data, out),
+        (4, 3) => decode_impl_stream::<_, 4, false>(state, data, out),
+        (4, 4) => decode_impl_stream::<_, 4, true>(state, data, out),
         _ => {
             cold();
             Err(Error::InvalidChannels { channels })
@@ -226,61 +726,247 @@ fn decode_impl_stream_all<R: Read>(
 
 #[doc(hidden)]
 pub trait Reader: Sized {
-    fn decode_header(&mut self) -> Result<Header>;
-    fn decode_image(&mut self, out: &mut [u8], channels: u8, src_channels: u8) -> Result<()>;
+    /// Decodes the header along with its raw channels and color space bytes, which aren't
+    /// validated.
+    fn decode_header(&mut self, endianness: Endianness) -> Result<(Header, [u8; 2])>;
+    fn decode_pixels(
+        &mut self, state: &mut DecodeState, out: &mut [u8], channels: u8, src_channels: u8,
+    ) -> Result<()>;
+    fn decode_padding(&mut self, allow_missing: bool) -> core::result::Result<(), PaddingError>;
+    /// Number of bytes left to read, if it can be determined.
+    fn remaining_len(&self) -> Option<usize>;
+    /// Number of channels implied by the op stream, if it can be determined.
+    fn infer_channels(&self, n_pixels: usize) -> Option<Channels>;
+}
+
+/// Reason why the end padding couldn't be validated.
+#[doc(hidden)]
+pub enum PaddingError {
+    /// The op stream is this many pixels short of the header, so that the decoder has run
+    /// into the padding; the error is what would be reported otherwise.
+    Missing(usize, Error),
+    /// The op stream has this many extra pixels, which have been skipped along with the padding.
+    Extra(usize),
+    /// The padding is invalid for any other reason.
+    Invalid(Error),
+}
+
+impl From<Error> for PaddingError {
+    #[inline]
+    fn from(err: Error) -> Self {
+        Self::Invalid(err)
+    }
 }
 
-pub struct Bytes<'a>(&'a [u8]);
+#[cfg(feature = "std")]
+impl From<std::io::Error> for PaddingError {
+    #[inline]
+    fn from(err: std::io::Error) -> Self {
+        Self::Invalid(err.into())
+    }
+}
+
+pub struct Bytes<'a>(&'a [u8], &'a [u8]); // remaining data, full op stream
 
 impl<'a> Bytes<'a> {
     #[inline]
     pub const fn new(buf: &'a [u8]) -> Self {
-        Self(buf)
+        Self(buf, buf)
     }
 
     #[inline]
     pub const fn as_slice(&self) -> &[u8] {
         self.0
     }
+
+    /// Figures out whether the op stream mismatches the header, given the error that
+    /// occurred while validating the padding at the current position.
+    #[cold]
+    fn diagnose_padding(&mut self, err: Error) -> PaddingError {
+        if self.0.len() < QOI_PADDING_SIZE && self.1.ends_with(&QOI_PADDING) {
+            // the padding consists of single-pixel ops, so the decoder has run into it
+            PaddingError::Missing(QOI_PADDING_SIZE - self.0.len(), err)
+        } else if let Some(n) = self.0.strip_suffix(&QOI_PADDING).and_then(count_op_pixels) {
+            self.0 = &self.0[self.0.len()..]; // can't panic
+            PaddingError::Extra(n)
+        } else {
+            PaddingError::Invalid(err)
+        }
+    }
+}
+
+/// Returns the length of an operation in bytes and the number of pixels it produces, given
+/// its first byte.
+#[inline]
+const fn op_size(b1: u8) -> (usize, usize) {
+    match b1 {
+        QOI_OP_RGB => (4, 1),
+        QOI_OP_RGBA => (5, 1),
+        QOI_OP_RUN..=QOI_OP_RUN_END => (1, (b1 & 0x3f) as usize + 1),
+        QOI_OP_LUMA..=QOI_OP_LUMA_END => (2, 1),
+        _ => (1, 1),
+    }
+}
+
+/// Returns the total length in bytes (including the tag) of the operation that starts with
+/// `first_byte`.
+///
+/// This is 1 for QOI_OP_INDEX, QOI_OP_DIFF and QOI_OP_RUN, 2 for QOI_OP_LUMA, 4 for
+/// QOI_OP_RGB and 5 for QOI_OP_RGBA, which is all that's needed to walk an op stream.
+#[inline]
+pub const fn op_length(first_byte: u8) -> usize {
+    op_size(first_byte).0
+}
+
+/// Counts the pixels produced by a sequence of operations (without the padding).
+///
+/// Returns `None` if the last operation is truncated.
+#[inline]
+fn count_op_pixels(mut ops: &[u8]) -> Option<usize> {
+    let mut n_pixels = 0;
+    while let [b1, ..] = ops {
+        let (len, count) = op_size(*b1);
+        ops = ops.get(len..)?;
+        n_pixels += count;
+    }
+    Some(n_pixels)
 }
 
-impl<'a> Reader for Bytes<'a> {
+impl Reader for Bytes<'_> {
     #[inline]
-    fn decode_header(&mut self) -> Result<Header> {
-        let header = Header::decode(self.0)?;
+    fn decode_header(&mut self, endianness: Endianness) -> Result<(Header, [u8; 2])> {
+        let header = Header::decode_raw(self.0, endianness)?;
         self.0 = &self.0[QOI_HEADER_SIZE..]; // can't panic
+        self.1 = self.0;
         Ok(header)
     }
 
     #[inline]
-    fn decode_image(&mut self, out: &mut [u8], channels: u8, src_channels: u8) -> Result<()> {
-        let n_read = decode_impl_slice_all(self.0, out, channels, src_channels)?;
-        self.0 = &self.0[n_read..];
+    fn decode_pixels(
+        &mut self, state: &mut DecodeState, out: &mut [u8], channels: u8, src_channels: u8,
+    ) -> Result<()> {
+        let n_read =
+            decode_impl_slice_all::<StandardHash>(state, self.0, out, channels, src_channels)?;
+        self.0 = &self.0[n_read..]; // can't panic
         Ok(())
     }
+
+    #[inline]
+    fn decode_padding(&mut self, allow_missing: bool) -> core::result::Result<(), PaddingError> {
+        let n = self.0.len().min(QOI_PADDING_SIZE);
+        if allow_missing && self.0[..n] == QOI_PADDING[..n] {
+            self.0 = &self.0[n..];
+            return Ok(());
+        }
+        if let Err(err) = decode_padding_slice(self.0) {
+            return Err(self.diagnose_padding(err));
+        }
+        self.0 = &self.0[QOI_PADDING_SIZE..]; // can't panic
+        Ok(())
+    }
+
+    #[inline]
+    fn remaining_len(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+
+    fn infer_channels(&self, n_pixels: usize) -> Option<Channels> {
+        let (mut data, mut remaining, mut has_alpha) = (self.1, n_pixels, false);
+        while remaining != 0 {
+            let b1 = *data.first()?;
+            let (len, count) = op_size(b1);
+            has_alpha |= b1 == QOI_OP_RGBA;
+            remaining = remaining.checked_sub(count)?;
+            data = data.get(len..)?;
+        }
+        decode_padding_slice(data).ok()?;
+        Some(if has_alpha { Channels::Rgba } else { Channels::Rgb })
+    }
 }
 
 #[cfg(feature = "std")]
 impl<R: Read> Reader for R {
     #[inline]
-    fn decode_header(&mut self) -> Result<Header> {
+    fn decode_header(&mut self, endianness: Endianness) -> Result<(Header, [u8; 2])> {
         let mut b = [0; QOI_HEADER_SIZE];
         self.read_exact(&mut b)?;
-        Header::decode(b)
+        Header::decode_raw(b, endianness)
     }
 
     #[inline]
-    fn decode_image(&mut self, out: &mut [u8], channels: u8, src_channels: u8) -> Result<()> {
-        decode_impl_stream_all(self, out, channels, src_channels)
+    fn decode_pixels(
+        &mut self, state: &mut DecodeState, out: &mut [u8], channels: u8, src_channels: u8,
+    ) -> Result<()> {
+        decode_impl_stream_all(state, self, out, channels, src_channels)
     }
+
+    #[inline]
+    fn decode_padding(&mut self, allow_missing: bool) -> core::result::Result<(), PaddingError> {
+        if allow_missing {
+            let mut p = Vec::with_capacity(QOI_PADDING_SIZE);
+            self.by_ref().take(QOI_PADDING_SIZE as u64).read_to_end(&mut p)?;
+            return if p[..] == QOI_PADDING[..p.len()] {
+                Ok(())
+            } else {
+                Err(Error::InvalidPadding.into())
+            };
+        }
+        decode_padding_stream(self)
+    }
+
+    #[inline]
+    fn remaining_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn infer_channels(&self, _n_pixels: usize) -> Option<Channels> {
+        None
+    }
+}
+
+/// Result of [`Decoder::try_decode_zero_copy`].
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeResult {
+    /// The image consists of a single color (RGBA) repeated the given number of times.
+    Solid([u8; 4], usize),
+    /// The fully decoded image.
+    Full(Vec<u8>),
 }
 
 /// Decode QOI images from slices or from streams.
 #[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Decoder<R> {
     reader: R,
     header: Header,
     channels: Channels,
+    state: DecodeState,
+    n_decoded: usize,
+    allow_missing_padding: bool,
+    check_pixel_count: bool,
+    check_trailing_bytes: bool,
+    raw_channels: u8,
+    raw_colorspace: u8,
+    clamp_colorspace: bool,
+    skip_trailing_chunks: bool,
+    scale: u32,
+    error_fill: Option<[u8; 4]>,
+    recovered: bool,
+}
+
+impl<R> Debug for Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+        f.debug_struct("Decoder")
+            .field("width", &header.width)
+            .field("height", &header.height)
+            .field("channels", &self.channels)
+            .field("colorspace", &header.colorspace)
+            .field("stride", &(header.width as usize * self.channels.bytes_per_pixel()))
+            .finish_non_exhaustive()
+    }
 }
 
 impl<'a> Decoder<Bytes<'a>> {
@@ -293,7 +979,43 @@ impl<'a> Decoder<Bytes<'a>> {
     /// stream, use [`Decoder::from_stream`] instead.
     #[inline]
     pub fn new(data: &'a (impl AsRef<[u8]> + ?Sized)) -> Result<Self> {
-        Self::new_impl(Bytes::new(data.as_ref()))
+        Self::new_impl(Bytes::new(data.as_ref()), Endianness::Big)
+    }
+
+    /// Creates a new decoder from a slice of bytes, deferring the validation of the channels
+    /// and color space bytes of the header until decoding.
+    ///
+    /// This is only needed for malformed headers that are meant to be tolerated via
+    /// [`Decoder::with_colorspace_clamp`] or [`Decoder::with_infer_channels`]; until then,
+    /// [`Decoder::header`] reports 4 channels for an invalid channels byte and the linear
+    /// color space for an invalid color space byte. [`Decoder::new`] should be used otherwise.
+    #[inline]
+    pub fn new_lenient(data: &'a (impl AsRef<[u8]> + ?Sized)) -> Result<Self> {
+        Self::new_lenient_impl(Bytes::new(data.as_ref()), Endianness::Big)
+    }
+
+    /// Creates a new decoder from a slice of bytes, reading the header dimensions in the
+    /// given byte order.
+    ///
+    /// This is only needed for non-conforming files that store the dimensions in
+    /// little-endian; [`Decoder::new`] should be used otherwise.
+    #[inline]
+    pub fn new_with_header_endianness(
+        data: &'a (impl AsRef<[u8]> + ?Sized), endianness: Endianness,
+    ) -> Result<Self> {
+        Self::new_impl(Bytes::new(data.as_ref()), endianness)
+    }
+
+    /// Creates a new decoder from a header and the op stream that follows it.
+    ///
+    /// This is useful for containers that store the header separately from the image data,
+    /// since the full file doesn't have to be reassembled. The `body` is expected to end
+    /// with the 8-byte padding, unless [`Decoder::with_allow_missing_padding`] is enabled.
+    #[inline]
+    pub fn from_parts(header: &Header, body: &'a [u8]) -> Result<Self> {
+        let header =
+            Header::try_new(header.width, header.height, header.channels, header.colorspace)?;
+        Ok(Self::from_header(Bytes::new(body), header))
     }
 
     /// Returns the undecoded tail of the input slice of bytes.
@@ -301,6 +1023,27 @@ impl<'a> Decoder<Bytes<'a>> {
     pub const fn data(&self) -> &[u8] {
         self.reader.as_slice()
     }
+
+    /// Returns the bytes that follow the end padding, e.g. extension chunks.
+    ///
+    /// This is only meaningful once the whole image has been decoded; until then, an empty
+    /// slice is returned.
+    #[inline]
+    pub const fn trailing_bytes(&self) -> &[u8] {
+        if self.header.n_pixels() == self.n_decoded && !self.recovered {
+            self.reader.as_slice()
+        } else {
+            &[]
+        }
+    }
+
+    /// Returns the number of input bytes that haven't been consumed yet.
+    ///
+    /// Note: a run operation is consumed as a whole as soon as its first pixel is decoded.
+    #[inline]
+    pub const fn bytes_remaining(&self) -> usize {
+        self.reader.as_slice().len()
+    }
 }
 
 #[cfg(feature = "std")]
@@ -313,7 +1056,26 @@ impl<R: Read> Decoder<R> {
     /// would be more efficient to use a specialized constructor instead: [`Decoder::new`].
     #[inline]
     pub fn from_stream(reader: R) -> Result<Self> {
-        Self::new_impl(reader)
+        Self::new_impl(reader, Endianness::Big)
+    }
+
+    /// Creates a new decoder from a generic reader, deferring the validation of the channels
+    /// and color space bytes of the header until decoding.
+    ///
+    /// See [`Decoder::new_lenient`] for details.
+    #[inline]
+    pub fn from_stream_lenient(reader: R) -> Result<Self> {
+        Self::new_lenient_impl(reader, Endianness::Big)
+    }
+
+    /// Creates a new decoder from a generic reader, reading the header dimensions in the
+    /// given byte order.
+    ///
+    /// This is only needed for non-conforming files that store the dimensions in
+    /// little-endian; [`Decoder::from_stream`] should be used otherwise.
+    #[inline]
+    pub fn from_stream_with_header_endianness(reader: R, endianness: Endianness) -> Result<Self> {
+        Self::new_impl(reader, endianness)
     }
 
     /// Returns an immutable reference to the underlying reader.
@@ -332,9 +1094,38 @@ impl<R: Read> Decoder<R> {
 
 impl<R: Reader> Decoder<R> {
     #[inline]
-    fn new_impl(mut reader: R) -> Result<Self> {
-        let header = reader.decode_header()?;
-        Ok(Self { reader, header, channels: header.channels })
+    fn new_impl(reader: R, endianness: Endianness) -> Result<Self> {
+        let decoder = Self::new_lenient_impl(reader, endianness)?;
+        let _ = Channels::try_from(decoder.raw_channels)?;
+        let _ = ColorSpace::try_from(decoder.raw_colorspace)?;
+        Ok(decoder)
+    }
+
+    #[inline]
+    fn new_lenient_impl(mut reader: R, endianness: Endianness) -> Result<Self> {
+        let (header, [raw_channels, raw_colorspace]) = reader.decode_header(endianness)?;
+        Ok(Self { raw_channels, raw_colorspace, ..Self::from_header(reader, header) })
+    }
+
+    #[inline]
+    const fn from_header(reader: R, header: Header) -> Self {
+        Self {
+            reader,
+            header,
+            channels: header.channels,
+            state: DecodeState::new(),
+            n_decoded: 0,
+            allow_missing_padding: false,
+            check_pixel_count: true,
+            check_trailing_bytes: false,
+            raw_channels: header.channels.as_u8(),
+            raw_colorspace: header.colorspace.as_u8(),
+            clamp_colorspace: false,
+            skip_trailing_chunks: false,
+            scale: 1,
+            error_fill: None,
+            recovered: false,
+        }
     }
 
     /// Returns a new decoder with modified number of channels.
@@ -349,6 +1140,166 @@ impl<R: Reader> Decoder<R> {
         self
     }
 
+    /// Returns a new decoder that upscales the image by an integer factor while decoding.
+    ///
+    /// Each pixel is replicated `factor` times horizontally and vertically, so the output
+    /// is `width * factor` by `height * factor` pixels, e.g. for displaying pixel art. This
+    /// applies to [`Decoder::decode_to_buf`], [`Decoder::decode_to_vec`] and the methods built
+    /// on top of them, as well as to [`Decoder::required_buf_len`]; the header and the other
+    /// decoding methods are not affected. A factor of zero is treated as one.
+    #[inline]
+    pub const fn with_integer_scale(mut self, factor: u32) -> Self {
+        self.scale = if factor == 0 { 1 } else { factor };
+        self
+    }
+
+    /// Returns a new decoder that accepts images with the end padding missing or truncated.
+    ///
+    /// By default, the 8-byte stream end marker is required, and its absence is reported as
+    /// an error. If enabled, the image is still decoded successfully as long as the pixels
+    /// are complete and the bytes that follow them (if any) match the start of the marker.
+    #[inline]
+    pub const fn with_allow_missing_padding(mut self, allow: bool) -> Self {
+        self.allow_missing_padding = allow;
+        self
+    }
+
+    /// Returns a new decoder that reports images whose op stream doesn't match the header.
+    ///
+    /// This is enabled by default: if decoding fails at the end because the operations
+    /// produce fewer or more pixels than declared in the header, the error is reported as
+    /// [`Error::PixelCountMismatch`]. If disabled, any operations that follow the last pixel
+    /// are ignored instead, whereas a pixel shortage is reported as whatever error occurred
+    /// while validating the padding. Note that a shortage of more than 8 pixels (the length
+    /// of the padding) is always reported as running out of data, and that a stream being
+    /// decoded has to be read to the end of the padding to detect extra pixels.
+    #[inline]
+    pub const fn with_pixel_count_check(mut self, check: bool) -> Self {
+        self.check_pixel_count = check;
+        self
+    }
+
+    /// Returns a new decoder that rejects images followed by any bytes after the end padding.
+    ///
+    /// By default, such bytes are ignored (which is how metadata can be appended to images,
+    /// see [`Encoder::with_trailer_metadata`](crate::Encoder::with_trailer_metadata)). If
+    /// enabled, they're reported as [`Error::TrailingBytes`]. Like the pixel count check, this
+    /// only applies to decoding from slices, since streams may legitimately contain more data.
+    #[inline]
+    pub const fn with_trailing_bytes_check(mut self, check: bool) -> Self {
+        self.check_trailing_bytes = check;
+        self
+    }
+
+    /// Returns a new decoder that accepts extension chunks appended after the end padding.
+    ///
+    /// If enabled, any bytes that follow the padding are never reported as
+    /// [`Error::TrailingBytes`], even if the trailing bytes check is enabled (e.g. via
+    /// [`Decoder::strict`]), so that the rest of the validation can still be applied. Once
+    /// the image has been decoded, the bytes can be retrieved via [`Decoder::trailing_bytes`].
+    #[inline]
+    pub const fn with_skip_trailing_chunks(mut self, skip: bool) -> Self {
+        self.skip_trailing_chunks = skip;
+        self
+    }
+
+    /// Returns a new decoder that recovers from errors in the op stream by filling the rest
+    /// of the image with the given RGBA color (alpha is ignored for 3-channel output).
+    ///
+    /// This is meant for best-effort display of damaged images: once an error is encountered
+    /// while decoding pixels (e.g. a truncated stream) or validating the end of the image
+    /// (e.g. a run overflow or invalid padding), the pixels decoded so far are kept, all the
+    /// remaining ones are filled, and decoding succeeds; [`Decoder::recovered`] then returns
+    /// true. Errors in the header are still reported. Note that pixels are decoded one at a
+    /// time in this mode, which is considerably slower.
+    #[inline]
+    pub const fn with_error_fill(mut self, color: [u8; 4]) -> Self {
+        self.error_fill = Some(color);
+        self
+    }
+
+    /// Returns a new decoder that tolerates garbage in the color space byte of the header.
+    ///
+    /// **This requires a lenient constructor**: [`Decoder::new`] and [`Decoder::from_stream`]
+    /// reject such headers with [`Error::InvalidColorSpace`] right away, so the decoder has
+    /// to be created via [`Decoder::new_lenient`] or [`Decoder::from_stream_lenient`], which
+    /// defer the error until decoding. If enabled, any non-zero value is treated as linear,
+    /// which is also what [`Decoder::header`] reports.
+    ///
+    /// ```
+    /// # fn main() -> qoi::Result<()> {
+    /// use qoi::{ColorSpace, Decoder};
+    ///
+    /// // a 1x1 RGBA image with a color space byte of 0x80, consisting of a single run
+    /// let data = b"qoif\0\0\0\x01\0\0\0\x01\x04\x80\xc0\0\0\0\0\0\0\0\x01";
+    /// assert!(Decoder::new(data).is_err());
+    ///
+    /// let mut decoder = Decoder::new_lenient(data)?.with_colorspace_clamp(true);
+    /// assert_eq!(decoder.header().colorspace, ColorSpace::Linear);
+    /// assert_eq!(decoder.decode_to_vec()?, [0, 0, 0, 255]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub const fn with_colorspace_clamp(mut self, clamp: bool) -> Self {
+        self.clamp_colorspace = clamp;
+        self
+    }
+
+    /// Returns a new decoder that infers the number of channels if the header has an invalid
+    /// channels byte (e.g. 0, as written by some broken encoders).
+    ///
+    /// **This requires a lenient constructor**: [`Decoder::new`] and [`Decoder::from_stream`]
+    /// reject such headers with [`Error::InvalidChannels`] right away, so the decoder has to
+    /// be created via [`Decoder::new_lenient`] or [`Decoder::from_stream_lenient`], which
+    /// defer the error until decoding.
+    ///
+    /// If enabled, the op stream is scanned up front: provided that it produces exactly the
+    /// number of pixels in the header followed by valid padding, the image is assumed to be
+    /// RGBA if it contains any QOI_OP_RGBA operations, and RGB otherwise (which is lossless
+    /// either way). Both the header and the output channels are updated then. Streams can't
+    /// be scanned ahead, so this only applies to decoding from slices.
+    ///
+    /// ```
+    /// # fn main() -> qoi::Result<()> {
+    /// use qoi::{Channels, Decoder};
+    ///
+    /// // a 1x1 image with a channels byte of 0, consisting of a single run
+    /// let data = b"qoif\0\0\0\x01\0\0\0\x01\0\0\xc0\0\0\0\0\0\0\0\x01";
+    /// assert!(Decoder::new(data).is_err());
+    ///
+    /// let mut decoder = Decoder::new_lenient(data)?.with_infer_channels(true);
+    /// assert_eq!(decoder.header().channels, Channels::Rgb);
+    /// assert_eq!(decoder.decode_to_vec()?, [0, 0, 0]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn with_infer_channels(mut self, infer: bool) -> Self {
+        if infer && !matches!(self.raw_channels, 3 | 4) {
+            if let Some(channels) = self.reader.infer_channels(self.header.n_pixels()) {
+                self.header.channels = channels;
+                self.channels = channels;
+                self.raw_channels = channels.as_u8();
+            }
+        }
+        self
+    }
+
+    /// Returns a new decoder with all of the validation options enabled.
+    ///
+    /// This requires the end padding to be present, the op stream to match the number of
+    /// pixels in the header, no bytes to follow the padding, and the color space to be valid
+    /// (see the individual options for details). Channels that have already been inferred via
+    /// [`Decoder::with_infer_channels`] are kept as is.
+    #[inline]
+    pub const fn strict(self) -> Self {
+        self.with_allow_missing_padding(false)
+            .with_pixel_count_check(true)
+            .with_trailing_bytes_check(true)
+            .with_colorspace_clamp(false)
+    }
+
     /// Returns the number of channels in the decoded image.
     ///
     /// Note: this may differ from the number of channels specified in the header.
@@ -363,12 +1314,113 @@ impl<R: Reader> Decoder<R> {
         &self.header
     }
 
+    /// Returns true if an error has been recovered from by filling the rest of the image,
+    /// see [`Decoder::with_error_fill`].
+    #[inline]
+    pub const fn recovered(&self) -> bool {
+        self.recovered
+    }
+
+    /// Returns the number of pixels that haven't been decoded yet.
+    #[inline]
+    pub const fn pixels_remaining(&self) -> usize {
+        self.header.n_pixels().saturating_sub(self.n_decoded)
+    }
+
+    #[inline]
+    fn decode_pixels(&mut self, out: &mut [u8], channels: u8) -> Result<()> {
+        if unlikely(!matches!(self.raw_channels, 3 | 4)) {
+            return Err(Error::InvalidChannels { channels: self.raw_channels });
+        }
+        if unlikely(self.raw_colorspace > 1 && !self.clamp_colorspace) {
+            return Err(Error::InvalidColorSpace { colorspace: self.raw_colorspace });
+        }
+        let src_channels = self.header.channels.as_u8();
+        if let Some(color) = self.error_fill {
+            self.decode_pixels_or_fill(out, channels, src_channels, color);
+        } else {
+            self.reader.decode_pixels(&mut self.state, out, channels, src_channels)?;
+        }
+        self.n_decoded += out.len() / channels as usize;
+        Ok(())
+    }
+
+    /// Decodes pixels one at a time, filling the rest of the output with the given color
+    /// after the first error (and from then on).
+    #[cold]
+    fn decode_pixels_or_fill(
+        &mut self, out: &mut [u8], channels: u8, src_channels: u8, color: [u8; 4],
+    ) {
+        let color = &color[..channels as usize];
+        for px in out.chunks_exact_mut(channels as usize) {
+            if !self.recovered {
+                let res = self.reader.decode_pixels(&mut self.state, px, channels, src_channels);
+                self.recovered = res.is_err();
+            }
+            if self.recovered {
+                px.copy_from_slice(color);
+            }
+        }
+    }
+
+    /// Validates the end of the image once all of its pixels have been decoded.
+    #[inline]
+    fn decode_padding(&mut self) -> Result<()> {
+        if self.error_fill.is_some() {
+            // nothing past the first error can be trusted, including the end of the stream
+            self.recovered = self.recovered || self.decode_padding_impl().is_err();
+            return Ok(());
+        }
+        self.decode_padding_impl()
+    }
+
+    #[inline]
+    fn decode_padding_impl(&mut self) -> Result<()> {
+        // a run that's still pending at this point would write past the end of the image
+        if unlikely(self.state.run != 0) {
+            return Err(Error::RunOverflow);
+        }
+        match self.reader.decode_padding(self.allow_missing_padding) {
+            Err(err) => self.diagnose_padding_error(err),
+            Ok(()) if self.check_trailing_bytes && !self.skip_trailing_chunks => {
+                match self.reader.remaining_len() {
+                    Some(size) if size != 0 => Err(Error::TrailingBytes { size }),
+                    _ => Ok(()),
+                }
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Checks whether the invalid padding is caused by the op stream length mismatching the
+    /// header, returning the original error otherwise.
+    #[cold]
+    #[inline(never)]
+    #[allow(clippy::missing_const_for_fn)] // errors can't be dropped in const fns with std
+    fn diagnose_padding_error(&self, err: PaddingError) -> Result<()> {
+        let expected = self.header.n_pixels();
+        match err {
+            PaddingError::Extra(_) if !self.check_pixel_count => Ok(()),
+            PaddingError::Extra(n) => {
+                Err(Error::PixelCountMismatch { got: expected + n, expected })
+            }
+            PaddingError::Missing(n, _) if self.check_pixel_count => {
+                Err(Error::PixelCountMismatch { got: expected.saturating_sub(n), expected })
+            }
+            PaddingError::Missing(_, err) | PaddingError::Invalid(err) => Err(err),
+        }
+    }
+
     /// The number of bytes the decoded image will take.
     ///
     /// Can be used to pre-allocate the buffer to decode the image into.
     #[inline]
     pub const fn required_buf_len(&self) -> usize {
-        self.header.n_pixels().saturating_mul(self.channels.as_u8() as usize)
+        let scale = self.scale as usize;
+        self.header
+            .n_pixels()
+            .saturating_mul(self.channels.bytes_per_pixel())
+            .saturating_mul(scale.saturating_mul(scale))
     }
 
     /// Decodes the image to a pre-allocated buffer and returns the number of bytes written.
@@ -381,7 +1433,113 @@ impl<R: Reader> Decoder<R> {
         if unlikely(buf.len() < size) {
             return Err(Error::OutputBufferTooSmall { size: buf.len(), required: size });
         }
-        self.reader.decode_image(buf, self.channels.as_u8(), self.header.channels.as_u8())?;
+        if self.scale == 1 {
+            self.decode_pixels(&mut buf[..size], self.channels.as_u8())?;
+        } else {
+            self.decode_scaled(&mut buf[..size])?;
+        }
+        self.decode_padding()?;
+        Ok(size)
+    }
+
+    /// Decodes the pixels into a buffer of the upscaled size, expanding each row in place.
+    #[inline]
+    fn decode_scaled(&mut self, buf: &mut [u8]) -> Result<()> {
+        let (scale, channels) = (self.scale as usize, self.channels.bytes_per_pixel());
+        let row_len = self.header.width as usize * channels;
+        for rows in buf.chunks_exact_mut(row_len * scale * scale) {
+            self.decode_pixels(&mut rows[..row_len], self.channels.as_u8())?;
+            // right to left, so that no pixel is overwritten before it has been copied
+            for x in (0..self.header.width as usize).rev() {
+                for i in (0..scale).rev() {
+                    rows.copy_within(x * channels..(x + 1) * channels, (x * scale + i) * channels);
+                }
+            }
+            for i in 1..scale {
+                rows.copy_within(..row_len * scale, i * row_len * scale);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the image to a pre-allocated buffer with each row starting at a multiple of
+    /// `alignment` bytes, and returns the row stride that was used.
+    ///
+    /// The stride is the row size rounded up to the alignment (e.g. 256 for D3D12 texture
+    /// uploads; an alignment of 0 is treated as 1). The padding bytes at the end of each row
+    /// are left untouched, and the last row doesn't need to be followed by any, so the buffer
+    /// must hold at least `stride * (height - 1) + width * channels` bytes.
+    #[inline]
+    pub fn decode_to_buf_aligned(
+        &mut self, mut buf: impl AsMut<[u8]>, alignment: usize,
+    ) -> Result<usize> {
+        let buf = buf.as_mut();
+        let (width, height) = (self.header.width as usize, self.header.height as usize);
+        let channels = self.channels.as_u8();
+        let row_len = width * channels as usize;
+        let alignment = alignment.max(1);
+        let stride = (row_len.saturating_add(alignment - 1) / alignment).saturating_mul(alignment);
+        let required = stride.saturating_mul(height - 1).saturating_add(row_len);
+        if unlikely(buf.len() < required) {
+            return Err(Error::OutputBufferTooSmall { size: buf.len(), required });
+        }
+        for row in buf[..required].chunks_mut(stride) {
+            self.decode_pixels(&mut row[..row_len], channels)?;
+        }
+        self.decode_padding()?;
+        Ok(stride)
+    }
+
+    /// Decodes the image into a pre-allocated, possibly uninitialized buffer and returns the
+    /// initialized part of it.
+    ///
+    /// Unlike [`Decoder::decode_to_vec`], this doesn't require zeroing the output first,
+    /// which matters for large images. Nothing is returned unless the whole image has been
+    /// decoded, so the returned slice is always fully initialized.
+    #[cfg(feature = "uninit")]
+    #[inline]
+    pub fn decode_to_uninit<'b>(&mut self, buf: &'b mut [MaybeUninit<u8>]) -> Result<&'b mut [u8]> {
+        let size = self.required_buf_len();
+        if unlikely(buf.len() < size) {
+            return Err(Error::OutputBufferTooSmall { size: buf.len(), required: size });
+        }
+        let buf = &mut buf[..size];
+        // SAFETY: `MaybeUninit<u8>` has the same layout as `u8`, and the decoder never reads
+        // from its output; the slice is only returned once every byte of it has been written.
+        #[allow(unsafe_code)]
+        let buf = unsafe { &mut *(buf as *mut [MaybeUninit<u8>] as *mut [u8]) };
+        self.decode_to_buf(&mut *buf)?;
+        Ok(buf)
+    }
+
+    /// Decodes the image into a pre-allocated 4-channel buffer with arbitrary channel layout.
+    ///
+    /// Each output channel `i` is taken from the component `pattern[i]` of the decoded pixel,
+    /// e.g. `[B, G, R, A]` produces BGRA, `[A, A, A, A]` replicates alpha, and `[R, G, B, One]`
+    /// produces RGBA with opaque alpha. Pixels are reordered row by row as they are decoded.
+    ///
+    /// The buffer must be able to hold `width * height * 4` bytes; returns the number of
+    /// bytes written.
+    #[inline]
+    pub fn decode_swizzled(
+        &mut self, pattern: [Component; 4], mut buf: impl AsMut<[u8]>,
+    ) -> Result<usize> {
+        let buf = buf.as_mut();
+        let size = self.header.n_pixels() * 4;
+        if unlikely(buf.len() < size) {
+            return Err(Error::OutputBufferTooSmall { size: buf.len(), required: size });
+        }
+        let row_len = self.header.width as usize * 4;
+        for row in buf[..size].chunks_exact_mut(row_len) {
+            self.decode_pixels(row, 4)?;
+            for px in row.chunks_exact_mut(4) {
+                let src = [px[0], px[1], px[2], px[3]];
+                for (out, c) in px.iter_mut().zip(pattern) {
+                    *out = c.pick(src);
+                }
+            }
+        }
+        self.decode_padding()?;
         Ok(size)
     }
 
@@ -389,8 +1547,318 @@ impl<R: Reader> Decoder<R> {
     #[cfg(any(feature = "std", feature = "alloc"))]
     #[inline]
     pub fn decode_to_vec(&mut self) -> Result<Vec<u8>> {
-        let mut out = vec![0; self.header.n_pixels() * self.channels.as_u8() as usize];
+        let mut out = vec![0; self.required_buf_len()];
         let _ = self.decode_to_buf(&mut out)?;
         Ok(out)
     }
+
+    /// Decodes the image into a newly allocated vector, also returning the final state of the
+    /// 64-entry color index.
+    ///
+    /// This helps debugging encoders: comparing the index state an encoder assumes against
+    /// the one the decoder ends up with pinpoints divergences. Index entries are always RGBA
+    /// (with the alpha of 255 for RGB images), regardless of the number of output channels.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn decode_to_vec_with_index(&mut self) -> Result<(Header, Vec<u8>, [[u8; 4]; 64])> {
+        let data = self.decode_to_vec()?;
+        let mut index = [[0; 4]; 64];
+        for (out, px) in index.iter_mut().zip(&self.state.index) {
+            *out = (*px).into();
+        }
+        Ok((self.header, data, index))
+    }
+
+    /// Decodes the image into a newly allocated vector and transposes it.
+    ///
+    /// This is meant for files written by tools that swap the image dimensions: the pixel
+    /// at `(x, y)` is moved to `(y, x)`, and the returned header has its width and height
+    /// swapped (and the number of channels set to that of the decoded image).
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn decode_transposed(&mut self) -> Result<(Header, Vec<u8>)> {
+        let (width, height) = (self.header.width as usize, self.header.height as usize);
+        let channels = self.channels.bytes_per_pixel();
+        let mut data = vec![0; width * height * channels];
+        self.decode_pixels(&mut data, self.channels.as_u8())?;
+        self.decode_padding()?;
+        let mut out = Vec::with_capacity(data.len());
+        for x in 0..width {
+            for y in 0..height {
+                out.extend_from_slice(&data[(y * width + x) * channels..][..channels]);
+            }
+        }
+        let header = Header { width: self.header.height, height: self.header.width, ..self.header };
+        Ok((header.with_channels(self.channels), out))
+    }
+
+    /// Decodes at most `max_pixels` of the next pixels and returns them, along with a flag
+    /// indicating whether the image has more pixels left to decode.
+    ///
+    /// Decoding can be resumed by calling this method again. Once the last pixel has been
+    /// decoded, the end padding is validated as well.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn decode_partial(&mut self, max_pixels: usize) -> Result<(Vec<u8>, bool)> {
+        let n_pixels = max_pixels.min(self.pixels_remaining());
+        let mut out = vec![0; n_pixels * self.channels.bytes_per_pixel()];
+        self.decode_pixels(&mut out, self.channels.as_u8())?;
+        let has_more = self.pixels_remaining() != 0;
+        if !has_more && n_pixels != 0 {
+            self.decode_padding()?;
+        }
+        Ok((out, has_more))
+    }
+
+    /// Returns an iterator over the remaining pixels as RGBA, along with their `(x, y)` coordinates.
+    ///
+    /// Pixels are decoded one at a time as the iterator advances. Once the last pixel has been
+    /// decoded, the end padding is validated as well; the iterator stops after the first error.
+    #[inline]
+    pub fn enumerate_pixels(&mut self) -> impl Iterator<Item = Result<(u32, u32, [u8; 4])>> + '_ {
+        let width = self.header.width as usize;
+        let mut failed = false;
+        iter::from_fn(move || {
+            if failed || self.pixels_remaining() == 0 {
+                return None;
+            }
+            let i = self.n_decoded;
+            let mut px = [0; 4];
+            let result = self.decode_pixels(&mut px, 4).and_then(|()| {
+                if self.pixels_remaining() == 0 {
+                    self.decode_padding()?;
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                Ok(((i % width) as u32, (i / width) as u32, px))
+            });
+            failed = result.is_err();
+            Some(result)
+        })
+    }
+
+    /// Decodes a nearest-neighbor preview keeping only every `step`-th pixel of every
+    /// `step`-th row.
+    ///
+    /// All pixels are still decoded to stay in sync with the stream, but only the sampled
+    /// ones are stored. Returns the preview dimensions, which are `ceil(width / step)` by
+    /// `ceil(height / step)`, and the pixel data. A step of zero is treated as one.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn decode_subsampled(&mut self, step: u32) -> Result<(u32, u32, Vec<u8>)> {
+        let step = step.max(1);
+        let (width, height) = (self.header.width, self.header.height);
+        let (out_width, out_height) = ((width + step - 1) / step, (height + step - 1) / step);
+        let channels = self.channels.bytes_per_pixel();
+        let mut out = Vec::with_capacity(out_width as usize * out_height as usize * channels);
+        self.decode_for_each_row(|y, row| {
+            if y % step as usize == 0 {
+                for px in row.chunks_exact(channels).step_by(step as usize) {
+                    out.extend_from_slice(px);
+                }
+            }
+            Ok(())
+        })?;
+        Ok((out_width, out_height, out))
+    }
+
+    /// Decodes the image, avoiding the full allocation if it is a single solid color.
+    ///
+    /// Solid images are returned as [`DecodeResult::Solid`] with the RGBA color (alpha is
+    /// set to 255 when decoding into 3 channels) and the number of pixels; only one row is
+    /// allocated in this case. All other images are returned as [`DecodeResult::Full`].
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn try_decode_zero_copy(&mut self) -> Result<DecodeResult> {
+        let channels = self.channels.as_u8();
+        let n_channels = channels as usize;
+        let row_len = self.header.width as usize * n_channels;
+        let mut row = vec![0; row_len];
+        let mut color = [0, 0, 0, 0xff];
+        for y in 0..self.header.height as usize {
+            self.decode_pixels(&mut row, channels)?;
+            if y == 0 {
+                color[..n_channels].copy_from_slice(&row[..n_channels]);
+            }
+            if row.chunks_exact(n_channels).all(|px| px == &color[..n_channels]) {
+                continue;
+            }
+            // not solid: replay the rows decoded so far and decode the rest in one go
+            let size = self.header.n_pixels() * n_channels;
+            let mut out = Vec::with_capacity(size);
+            for _ in 0..y * self.header.width as usize {
+                out.extend_from_slice(&color[..n_channels]);
+            }
+            out.extend_from_slice(&row);
+            out.resize(size, 0);
+            let tail = &mut out[(y + 1) * row_len..];
+            self.decode_pixels(tail, channels)?;
+            self.decode_padding()?;
+            return Ok(DecodeResult::Full(out));
+        }
+        self.decode_padding()?;
+        Ok(DecodeResult::Solid(color, self.header.n_pixels()))
+    }
+
+    /// Decodes the image row by row, passing each decoded row to a callback.
+    ///
+    /// The callback receives the row index and the decoded bytes of that row; the row
+    /// buffer is allocated once and reused, so the full image is never held in memory.
+    /// If the callback returns an error, decoding is aborted and the error is returned.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn decode_for_each_row(
+        &mut self, mut f: impl FnMut(usize, &[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let channels = self.channels.as_u8();
+        let mut row = vec![0; self.header.width as usize * channels as usize];
+        for y in 0..self.header.height as usize {
+            self.decode_pixels(&mut row, channels)?;
+            f(y, &row)?;
+        }
+        self.decode_padding()
+    }
+
+    /// Decodes the image into a newly allocated vector of channel values normalized to `[0, 1]`.
+    ///
+    /// If `linearize` is set and the image is tagged as sRGB, the color channels are also
+    /// converted to linear light via the sRGB transfer function (alpha is left as is). The
+    /// returned header has its number of channels set to that of the decoded image.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn decode_to_f32_vec(&mut self, linearize: bool) -> Result<(Header, Vec<f32>)> {
+        let linearize = linearize && self.header.colorspace.is_srgb();
+        let (mut alpha_lut, mut color_lut) = ([0_f32; 256], [0_f32; 256]);
+        for (i, (a, c)) in (0..=255_u8).zip(alpha_lut.iter_mut().zip(&mut color_lut)) {
+            *a = f32::from(i) / 255.;
+            *c = if !linearize {
+                *a
+            } else if *a <= 0.040_45 {
+                *a / 12.92
+            } else {
+                ((*a + 0.055) / 1.055).powf(2.4)
+            };
+        }
+        let channels = self.channels.bytes_per_pixel();
+        let mut out = Vec::with_capacity(self.required_buf_len());
+        self.decode_for_each_row(|_, row| {
+            for px in row.chunks_exact(channels) {
+                out.extend(px[..3].iter().map(|&c| color_lut[c as usize]));
+                out.extend(px[3..].iter().map(|&c| alpha_lut[c as usize]));
+            }
+            Ok(())
+        })?;
+        Ok((self.header.with_channels(self.channels), out))
+    }
+
+    /// Decodes the image into a newly allocated vector of packed little-endian RGB565 pixels.
+    ///
+    /// Each pixel takes 2 bytes, with the 5 most significant bits of red in the high bits and
+    /// the 5 most significant bits of blue in the low bits (6 bits are kept for green), which
+    /// matches many microcontroller framebuffers; alpha is dropped. The rows are converted as
+    /// they are decoded, and the returned header is that of the encoded image.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub fn decode_to_rgb565_vec(&mut self) -> Result<(Header, Vec<u8>)> {
+        let channels = self.channels.bytes_per_pixel();
+        let mut out = Vec::with_capacity(self.header.n_pixels() * 2);
+        self.decode_for_each_row(|_, row| {
+            for px in row.chunks_exact(channels) {
+                let (r, g, b) =
+                    (u16::from(px[0] >> 3), u16::from(px[1] >> 2), u16::from(px[2] >> 3));
+                out.extend_from_slice(&(r << 11 | g << 5 | b).to_le_bytes());
+            }
+            Ok(())
+        })?;
+        Ok((self.header, out))
+    }
+}
+
+/// Decoder for images that arrive in pieces, e.g. over a slow link.
+///
+/// Each call to [`ProgressiveDecoder::decode_available`] receives all of the bytes that are
+/// available so far, and decodes as many whole operations as possible, so that partially
+/// received images can be displayed progressively via [`ProgressiveDecoder::pixels`].
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[derive(Clone, Default)]
+pub struct ProgressiveDecoder {
+    header: Option<Header>,
+    state: DecodeState,
+    pixels: Vec<u8>,
+    n_decoded: usize,
+    n_consumed: usize,
+    complete: bool,
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+impl ProgressiveDecoder {
+    /// Creates a new decoder that hasn't received any bytes yet.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes as much of the image as the bytes received so far allow.
+    ///
+    /// `input_so_far` must contain the whole image received up to this point, starting
+    /// with the header; bytes that have already been decoded are skipped. Returns the total
+    /// number of pixels decoded so far, and whether the image is complete (including the
+    /// end padding).
+    pub fn decode_available(&mut self, input_so_far: &[u8]) -> Result<(usize, bool)> {
+        if self.complete {
+            return Ok((self.n_decoded, true));
+        }
+        let header = match self.header {
+            Some(header) => header,
+            None if input_so_far.len() < QOI_HEADER_SIZE => return Ok((0, false)),
+            None => {
+                let header = Header::decode(input_so_far)?;
+                self.pixels = vec![0; header.n_bytes()];
+                self.n_consumed = QOI_HEADER_SIZE;
+                *self.header.insert(header)
+            }
+        };
+        let available = input_so_far.get(self.n_consumed..).unwrap_or_default();
+        let remaining = header.n_pixels() - self.n_decoded;
+        let (mut len, mut count) = (0, 0);
+        while count < remaining {
+            let (op_len, op_count) = match available.get(len) {
+                Some(&b1) => op_size(b1),
+                None => break,
+            };
+            if len + op_len > available.len() {
+                break;
+            }
+            if unlikely(count + op_count > remaining) {
+                return Err(Error::RunOverflow);
+            }
+            len += op_len;
+            count += op_count;
+        }
+        let channels = header.channels.as_u8();
+        let start = self.n_decoded * header.channels.bytes_per_pixel();
+        let out = &mut self.pixels[start..start + count * header.channels.bytes_per_pixel()];
+        let data = &available[..len];
+        let n_read =
+            decode_impl_slice_all::<StandardHash>(&mut self.state, data, out, channels, channels)?;
+        self.n_consumed += n_read;
+        self.n_decoded += count;
+        if self.n_decoded == header.n_pixels() && available.len() - n_read >= QOI_PADDING_SIZE {
+            decode_padding_slice(&available[n_read..])?;
+            self.complete = true;
+        }
+        Ok((self.n_decoded, self.complete))
+    }
+
+    /// Returns the header, once it has been received.
+    #[inline]
+    pub const fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Returns the pixels decoded so far, in the order of the image rows.
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        let bpp = self.header.map_or(0, |h| h.channels.bytes_per_pixel());
+        &self.pixels[..self.n_decoded * bpp]
+    }
 }
@@ -1,99 +1,504 @@
 #[cfg(any(feature = "std", feature = "alloc"))]
-use alloc::{vec, vec::Vec};
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::cmp::Ordering;
+#[cfg(any(feature = "std", feature = "alloc"))]
+use core::cmp::Reverse;
 use core::convert::TryFrom;
+use core::fmt::{self, Debug};
+use core::iter;
+use core::mem;
 #[cfg(feature = "std")]
-use std::io::Write;
+use std::{io::Write, path::Path};
 
-use bytemuck::Pod;
+use bytemuck::{cast_slice, Pod};
 
-use crate::consts::{QOI_HEADER_SIZE, QOI_OP_INDEX, QOI_OP_RUN, QOI_PADDING, QOI_PADDING_SIZE};
+#[cfg(any(feature = "alloc", feature = "std"))]
+use crate::consts::QOI_PREWARM_MAGIC;
+use crate::consts::{
+    QOI_HEADER_SIZE, QOI_OP_INDEX, QOI_OP_RGB, QOI_OP_RGBA, QOI_OP_RUN, QOI_PADDING,
+    QOI_PADDING_SIZE, QOI_TRAILER_MAX_LEN,
+};
 use crate::error::{Error, Result};
 use crate::header::Header;
-use crate::pixel::{Pixel, SupportedChannels};
-use crate::types::{Channels, ColorSpace};
+use crate::pixel::{ColorHash, Pixel, StandardHash, SupportedChannels};
+#[cfg(feature = "std")]
+use crate::pool::{BufferPool, PooledBuffer};
+use crate::stats::{ChannelOrderHint, EncodeStats};
+#[cfg(any(feature = "alloc", feature = "std"))]
+use crate::types::Layout;
+use crate::types::{Channels, ColorSpace, CompatMode, Component, Endianness, OpKind, RawChannels};
+use crate::utils::{unlikely, ArrayBuf, BytesMut, CountingWriter, Writer};
 #[cfg(feature = "std")]
-use crate::utils::GenericWriter;
-use crate::utils::{unlikely, BytesMut, Writer};
+use crate::utils::{GenericWriter, LimitWriter};
+
+/// Whether the reference encoder algorithm is followed by default.
+const REFERENCE: bool = cfg!(feature = "reference");
+
+/// Encoder state that persists between pixels, so that an image can be encoded in steps.
+///
+/// Note: the color index is kept separately, otherwise the compiler fails to keep the
+/// state in registers which makes encoding noticeably slower.
+#[derive(Copy, Clone)]
+struct EncodeState<const N: usize> {
+    px_prev: Pixel<N>,
+    hash_prev: u8,
+    run: u8,
+    index_allowed: bool,
+}
 
-#[allow(clippy::cast_possible_truncation, unused_assignments, unused_variables)]
-fn encode_impl<W: Writer, const N: usize>(mut buf: W, data: &[u8]) -> Result<usize>
+impl<const N: usize> EncodeState<N>
 where
     Pixel<N>: SupportedChannels,
     [u8; N]: Pod,
 {
-    let cap = buf.capacity();
-
-    let mut index = [Pixel::new(); 256];
-    let mut px_prev = Pixel::new().with_a(0xff);
-    let mut hash_prev = px_prev.hash_index();
-    let mut run = 0_u8;
-    let mut px = Pixel::<N>::new().with_a(0xff);
-    let mut index_allowed = false;
-
-    let n_pixels = data.len() / N;
+    #[inline]
+    fn new() -> Self {
+        let px_prev = Pixel::new().with_a(0xff);
+        let hash_prev = px_prev.hash_index();
+        Self { px_prev, hash_prev, run: 0, index_allowed: false }
+    }
 
-    for (i, chunk) in data.chunks_exact(N).enumerate() {
-        px.read(chunk);
-        if px == px_prev {
-            run += 1;
-            if run == 62 || unlikely(i == n_pixels - 1) {
-                buf = buf.write_one(QOI_OP_RUN | (run - 1))?;
-                run = 0;
+    /// Encodes the `i`-th pixel; the trailing run is flushed if it's the last pixel.
+    ///
+    /// If `INDEX` is false, QOI_OP_INDEX operations are never emitted; if `REF` is true, the
+    /// output matches the reference encoder exactly. Colors are indexed via `H`.
+    #[inline(always)]
+    #[allow(clippy::cast_possible_truncation)]
+    fn encode_px<W: Writer, H: ColorHash, const INDEX: bool, const REF: bool>(
+        &mut self, index: &mut [Pixel<4>; 256], mut buf: W, px: Pixel<N>, i: usize, is_last: bool,
+        trace: &mut impl FnMut(usize, OpKind),
+    ) -> Result<W> {
+        if px == self.px_prev {
+            self.run += 1;
+            if self.run == 62 || unlikely(is_last) {
+                buf = buf.write_one(QOI_OP_RUN | (self.run - 1))?;
+                trace(i + 1 - self.run as usize, OpKind::Run);
+                self.run = 0;
             }
         } else {
-            if run != 0 {
-                #[cfg(not(feature = "reference"))]
-                {
-                    // credits for the original idea: @zakarumych (had to be fixed though)
-                    buf = buf.write_one(if run == 1 && index_allowed {
-                        QOI_OP_INDEX | hash_prev
-                    } else {
-                        QOI_OP_RUN | (run - 1)
-                    })?;
-                }
-                #[cfg(feature = "reference")]
-                {
-                    buf = buf.write_one(QOI_OP_RUN | (run - 1))?;
-                }
-                run = 0;
+            if self.run != 0 {
+                // credits for the original idea: @zakarumych (had to be fixed though)
+                let (op, kind) = if INDEX && !REF && self.run == 1 && self.index_allowed {
+                    (QOI_OP_INDEX | self.hash_prev, OpKind::Index)
+                } else {
+                    (QOI_OP_RUN | (self.run - 1), OpKind::Run)
+                };
+                buf = buf.write_one(op)?;
+                trace(i - self.run as usize, kind);
+                self.run = 0;
             }
-            index_allowed = true;
+            self.index_allowed = true;
             let px_rgba = px.as_rgba(0xff);
-            hash_prev = px_rgba.hash_index();
-            let index_px = &mut index[hash_prev as usize];
-            if *index_px == px_rgba {
-                buf = buf.write_one(QOI_OP_INDEX | hash_prev)?;
+            self.hash_prev = H::hash_index(px_rgba.into()) & 0x3f;
+            let index_px = &mut index[self.hash_prev as usize];
+            if INDEX && *index_px == px_rgba {
+                buf = buf.write_one(QOI_OP_INDEX | self.hash_prev)?;
+                trace(i, OpKind::Index);
             } else {
                 *index_px = px_rgba;
-                buf = px.encode_into(px_prev, buf)?;
+                let (out, kind) = px.encode_into(self.px_prev, buf)?;
+                buf = out;
+                trace(i, kind);
             }
-            px_prev = px;
+            self.px_prev = px;
         }
+        Ok(buf)
     }
+}
 
+fn encode_impl<W: Writer, I, T, H, const N: usize, const INDEX: bool, const REF: bool>(
+    mut buf: W, n_pixels: usize, pixels: I, mut trace: T,
+) -> Result<usize>
+where
+    I: Iterator<Item = [u8; N]>,
+    T: FnMut(usize, OpKind),
+    H: ColorHash,
+    Pixel<N>: SupportedChannels,
+    [u8; N]: Pod,
+{
+    let cap = buf.capacity();
+    let mut state = EncodeState::<N>::new();
+    let mut index = [Pixel::new(); 256];
+    for (i, px) in pixels.enumerate() {
+        let (px, is_last) = (Pixel::from(px), i == n_pixels - 1);
+        buf = state.encode_px::<_, H, INDEX, REF>(&mut index, buf, px, i, is_last, &mut trace)?;
+    }
     buf = buf.write_many(&QOI_PADDING)?;
     Ok(cap.saturating_sub(buf.capacity()))
 }
 
+/// Source of pixel data for the encoder.
+#[derive(Copy, Clone)]
+enum Source<'a> {
+    /// Interleaved pixels, 3 or 4 bytes per pixel
+    Interleaved(&'a [u8]),
+    /// Separate equally-sized planes for each channel
+    Planes { r: &'a [u8], g: &'a [u8], b: &'a [u8], a: Option<&'a [u8]> },
+    /// Palette indices, one byte per pixel, along with an RGBA palette
+    Indexed { indices: &'a [u8], palette: &'a [[u8; 4]] },
+    /// Interleaved pixels stored column by column, 3 or 4 bytes per pixel
+    ColumnMajor { data: &'a [u8], width: usize, height: usize },
+    /// Interleaved pixels in the given layout with rows starting every `stride` bytes
+    Strided { data: &'a [u8], width: usize, stride: usize, raw: RawChannels },
+    /// Pixels generated on the fly by a function of `(x, y)`
+    Fn { f: &'a dyn Fn(u32, u32) -> [u8; 4], width: u32 },
+}
+
+/// Converts a raw pixel in the given layout to RGBA (with the alpha of 0xff for 3 channels).
 #[inline]
-fn encode_impl_all<W: Writer>(out: W, data: &[u8], channels: Channels) -> Result<usize> {
-    match channels {
-        Channels::Rgb => encode_impl::<_, 3>(out, data),
-        Channels::Rgba => encode_impl::<_, 4>(out, data),
+fn raw_to_rgba(raw: RawChannels, px: &[u8]) -> [u8; 4] {
+    if raw == RawChannels::Rgb565 {
+        let [r, g, b] = unpack_rgb565([px[0], px[1]]);
+        return [r, g, b, 0xff];
+    }
+    let mut out = [0xff; 4];
+    for (&c, &v) in raw.pattern().iter().zip(px) {
+        match c {
+            Component::R => out[0] = v,
+            Component::G => out[1] = v,
+            Component::B => out[2] = v,
+            _ => out[3] = v,
+        }
+    }
+    out
+}
+
+/// Expands a packed little-endian RGB565 pixel to 8 bits per channel by bit replication.
+#[inline]
+#[allow(clippy::cast_possible_truncation)]
+const fn unpack_rgb565(px: [u8; 2]) -> [u8; 3] {
+    let v = u16::from_le_bytes(px);
+    let (r, g, b) = ((v >> 11) as u8, (v >> 5) as u8 & 0x3f, v as u8 & 0x1f);
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// Per-pixel transforms applied to the source before encoding.
+#[derive(Copy, Clone)]
+struct Transform {
+    unpremultiply: bool,
+    mask: u8,
+    alpha_threshold: Option<u8>,
+}
+
+impl Transform {
+    #[inline]
+    const fn new() -> Self {
+        Self { unpremultiply: false, mask: 0xff, alpha_threshold: None }
+    }
+
+    #[inline]
+    const fn is_identity(self) -> bool {
+        !self.unpremultiply && self.mask == 0xff && self.alpha_threshold.is_none()
+    }
+
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn apply<const N: usize>(self, mut px: [u8; N]) -> [u8; N] {
+        if self.unpremultiply && N == 4 {
+            let a = u32::from(px[3]);
+            for c in &mut px[..3] {
+                // fully transparent pixels (a == 0) are left unchanged
+                if let Some(v) = (u32::from(*c) * 255 + a / 2).checked_div(a) {
+                    *c = v.min(255) as u8;
+                }
+            }
+        }
+        let mut out = px.map(|c| c & self.mask);
+        if let (Some(t), 4) = (self.alpha_threshold, N) {
+            out[3] = if px[3] >= t { 0xff } else { 0 };
+        }
+        out
+    }
+
+    /// Applies the transforms to an RGBA pixel of an image with the given number of channels,
+    /// keeping the alpha of 3-channel images at 0xff (it's part of the encoder state).
+    #[inline]
+    fn apply_rgba(self, px: [u8; 4], channels: Channels) -> [u8; 4] {
+        let px = self.apply(px);
+        if channels == Channels::Rgb {
+            [px[0], px[1], px[2], 0xff]
+        } else {
+            px
+        }
+    }
+}
+
+/// Restrictions on the operations emitted by the encoder.
+#[derive(Copy, Clone)]
+struct Ops {
+    raw: bool,
+    no_index: bool,
+    compat: CompatMode,
+}
+
+impl Ops {
+    #[inline]
+    const fn new() -> Self {
+        Self { raw: false, no_index: false, compat: CompatMode::DEFAULT }
+    }
+}
+
+/// Writes a single pixel as a QOI_OP_RGB or QOI_OP_RGBA operation, depending on its length.
+#[inline]
+fn encode_raw_px<W: Writer>(buf: W, px: &[u8]) -> Result<(W, OpKind)> {
+    if px.len() == 3 {
+        Ok((buf.write_many(&[QOI_OP_RGB, px[0], px[1], px[2]])?, OpKind::Rgb))
+    } else {
+        Ok((buf.write_many(&[QOI_OP_RGBA, px[0], px[1], px[2], px[3]])?, OpKind::Rgba))
+    }
+}
+
+fn encode_impl_raw<W: Writer, I, T, const N: usize>(
+    mut buf: W, pixels: I, mut trace: T,
+) -> Result<usize>
+where
+    I: Iterator<Item = [u8; N]>,
+    T: FnMut(usize, OpKind),
+{
+    let cap = buf.capacity();
+    for (i, px) in pixels.enumerate() {
+        let (out, kind) = encode_raw_px(buf, &px)?;
+        buf = out;
+        trace(i, kind);
+    }
+    buf = buf.write_many(&QOI_PADDING)?;
+    Ok(cap.saturating_sub(buf.capacity()))
+}
+
+#[inline]
+fn encode_transformed<W: Writer, I, T, H, const N: usize>(
+    buf: W, n_pixels: usize, pixels: I, trace: T, transform: Transform, ops: Ops,
+) -> Result<usize>
+where
+    I: Iterator<Item = [u8; N]>,
+    T: FnMut(usize, OpKind),
+    H: ColorHash,
+    Pixel<N>: SupportedChannels,
+    [u8; N]: Pod,
+{
+    if ops.raw {
+        encode_impl_raw(buf, pixels.map(|px| transform.apply(px)), trace)
+    } else if ops.no_index {
+        let pixels = pixels.map(|px| transform.apply(px));
+        encode_impl::<_, _, _, H, N, false, REFERENCE>(buf, n_pixels, pixels, trace)
+    } else if ops.compat == CompatMode::Spec {
+        let pixels = pixels.map(|px| transform.apply(px));
+        encode_impl::<_, _, _, H, N, true, true>(buf, n_pixels, pixels, trace)
+    } else if transform.is_identity() {
+        encode_impl::<_, _, _, H, N, true, false>(buf, n_pixels, pixels, trace)
+    } else {
+        let pixels = pixels.map(|px| transform.apply(px));
+        encode_impl::<_, _, _, H, N, true, false>(buf, n_pixels, pixels, trace)
+    }
+}
+
+impl Source<'_> {
+    /// Returns the `i`-th pixel as RGBA (alpha is set to 255 if there are 3 channels).
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn pixel(self, i: usize, channels: Channels) -> [u8; 4] {
+        match self {
+            Self::Interleaved(data) => {
+                let n = channels.bytes_per_pixel();
+                let px = &data[i * n..][..n];
+                [px[0], px[1], px[2], if n == 4 { px[3] } else { 0xff }]
+            }
+            Self::Planes { r, g, b, a } => [r[i], g[i], b[i], a.map_or(0xff, |a| a[i])],
+            Self::Indexed { indices, palette } => palette[indices[i] as usize],
+            Self::ColumnMajor { data, width, height } => {
+                let j = (i % width) * height + i / width;
+                Self::Interleaved(data).pixel(j, channels)
+            }
+            Self::Strided { data, width, stride, raw } => {
+                let n = raw.bytes_per_pixel();
+                let row = &data[(i / width) * stride..];
+                raw_to_rgba(raw, &row[(i % width) * n..][..n])
+            }
+            Self::Fn { f, width } => {
+                let width = width as usize;
+                let mut px = f((i % width) as u32, (i / width) as u32);
+                if channels == Channels::Rgb {
+                    px[3] = 0xff;
+                }
+                px
+            }
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn encode<W: Writer, T, H, const N: usize>(
+        self, buf: W, n_pixels: usize, trace: T, transform: Transform, ops: Ops,
+    ) -> Result<usize>
+    where
+        T: FnMut(usize, OpKind),
+        H: ColorHash,
+        Pixel<N>: SupportedChannels,
+        [u8; N]: Pod,
+    {
+        match self {
+            Self::Interleaved(data) => {
+                let pixels = cast_slice::<_, [u8; N]>(data).iter().copied();
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+            Self::Planes { r, g, b, a } => {
+                let a = a.unwrap_or(r); // only read if N == 4
+                let pixels = r.iter().zip(g).zip(b).zip(a).map(|(((&r, &g), &b), &a)| {
+                    let mut px = [a; N];
+                    px[..3].copy_from_slice(&[r, g, b]);
+                    px
+                });
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+            Self::Indexed { indices, palette } => {
+                let pixels = indices.iter().map(|&i| {
+                    let mut px = [0; N];
+                    px.copy_from_slice(&palette[i as usize][..N]);
+                    px
+                });
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+            Self::ColumnMajor { data, height, .. } => {
+                let columns = cast_slice::<_, [u8; N]>(data);
+                let pixels = (0..height).flat_map(|y| columns[y..].iter().step_by(height).copied());
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+            Self::Strided { data, width, stride, raw } if raw == raw.channels().into() => {
+                let rows = data.chunks(stride).take(n_pixels / width);
+                let rows = rows.map(|row| cast_slice::<_, [u8; N]>(&row[..width * N]));
+                let pixels = rows.flat_map(|row| row.iter().copied());
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+            Self::Strided { data, width, stride, raw } => {
+                let (rows, n) = (data.chunks(stride).take(n_pixels / width), raw.bytes_per_pixel());
+                let pixels = rows.flat_map(move |row| {
+                    row[..width * n].chunks_exact(n).map(move |px| {
+                        let mut out = [0; N];
+                        out.copy_from_slice(&raw_to_rgba(raw, px)[..N]);
+                        out
+                    })
+                });
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+            Self::Fn { f, width } => {
+                let height = (n_pixels / width as usize) as u32;
+                let pixels = (0..height).flat_map(|y| {
+                    (0..width).map(move |x| {
+                        let mut px = [0; N];
+                        px.copy_from_slice(&f(x, y)[..N]);
+                        px
+                    })
+                });
+                encode_transformed::<_, _, _, H, N>(buf, n_pixels, pixels, trace, transform, ops)
+            }
+        }
+    }
+}
+
+impl Encoder<'_> {
+    #[inline]
+    fn encode_impl_all<W: Writer>(&self, out: W) -> Result<usize> {
+        self.encode_impl_all_traced(out, |_, _| ())
+    }
+
+    #[inline]
+    fn encode_impl_all_traced<W: Writer>(
+        &self, out: W, trace: impl FnMut(usize, OpKind),
+    ) -> Result<usize> {
+        self.encode_impl_all_hashed::<_, StandardHash>(out, trace)
+    }
+
+    #[inline]
+    fn encode_impl_all_hashed<W: Writer, H: ColorHash>(
+        &self, out: W, trace: impl FnMut(usize, OpKind),
+    ) -> Result<usize> {
+        let (source, n_pixels) = (self.source, self.header.n_pixels());
+        let (transform, ops) = (self.transform, self.ops);
+        match self.header.channels {
+            Channels::Rgb => source.encode::<_, _, H, 3>(out, n_pixels, trace, transform, ops),
+            Channels::Rgba => source.encode::<_, _, H, 4>(out, n_pixels, trace, transform, ops),
+        }
     }
 }
 
 /// The maximum number of bytes the encoded image will take.
 ///
 /// Can be used to pre-allocate the buffer to encode the image into.
+///
+/// Note: any number of channels other than 3 is treated as 4 (the worst case), so that
+/// the returned bound is always large enough regardless of the value passed in.
 #[inline]
 pub fn encode_max_len(width: u32, height: u32, channels: impl Into<u8>) -> usize {
     let (width, height) = (width as usize, height as usize);
     let n_pixels = width.saturating_mul(height);
+    let channels = if channels.into() == 3 { 3 } else { 4 };
     QOI_HEADER_SIZE
-        + n_pixels.saturating_mul(channels.into() as usize)
-        + n_pixels
-        + QOI_PADDING_SIZE
+        .saturating_add(n_pixels.saturating_mul(channels + 1))
+        .saturating_add(QOI_PADDING_SIZE)
+}
+
+/// The smallest possible number of bytes the encoded image can take, over all valid op choices.
+///
+/// Every op other than a run inserts its pixel into the color index, so the decoder state
+/// after each pixel (the previous pixel and the color index) doesn't depend on which op was
+/// used, with a single exception: a run of the initial previous pixel (opaque black) leaves
+/// it out of the index, while encoding the first such pixel as a zero QOI_OP_DIFF inserts it
+/// (at the cost of one byte more for some run lengths). Which one is better depends on
+/// whether the color reappears later, so both options are evaluated (in two passes); other
+/// than that, a maximal run of `k` repeated pixels costs `ceil(k / 62)` bytes, and any other
+/// pixel costs its cheapest op.
+///
+/// Returns [`Error::InvalidChannels`] if the number of channels is neither 3 nor 4, and
+/// [`Error::InvalidImageLength`] if the data doesn't contain exactly `width * height` pixels.
+#[allow(clippy::missing_panics_doc)]
+pub fn theoretical_min_size(
+    data: &[u8], width: u32, height: u32, channels: impl Into<u8>,
+) -> Result<usize> {
+    let channels = Channels::try_from(channels.into())?;
+    let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+    let n = channels.bytes_per_pixel();
+    if unlikely(data.len() != header.n_pixels() * n) {
+        return Err(Error::InvalidImageLength { size: data.len(), width, height });
+    }
+    let pixels = || {
+        let px = |c: &[u8]| Pixel::from([c[0], c[1], c[2], c.get(3).map_or(0xff, |&a| a)]);
+        data.chunks_exact(n).map(px)
+    };
+    let size = min_ops_len(pixels(), false).min(min_ops_len(pixels(), true));
+    Ok(QOI_HEADER_SIZE + size + QOI_PADDING_SIZE)
+}
+
+/// The smallest total length of the ops encoding the pixels. If `warm` is set and the first
+/// pixel is the initial previous pixel, it's encoded as a zero QOI_OP_DIFF instead of being
+/// part of a run, which inserts it into the color index.
+fn min_ops_len(pixels: impl Iterator<Item = Pixel<4>>, warm: bool) -> usize {
+    let mut pixels = pixels.peekable();
+    let mut index = [Pixel::<4>::new(); 64];
+    let (mut px_prev, mut run, mut size) = (Pixel::<4>::new().with_a(0xff), 0, 0);
+    if warm && pixels.peek() == Some(&px_prev) {
+        let _ = pixels.next();
+        index[px_prev.hash_index() as usize] = px_prev;
+        size += 1;
+    }
+    for px in pixels {
+        if px == px_prev {
+            run += 1;
+            continue;
+        }
+        size += (run + 61) / 62;
+        run = 0;
+        let slot = &mut index[px.hash_index() as usize];
+        if *slot == px {
+            size += 1;
+        } else {
+            *slot = px;
+            // counting writer is infallible
+            let (counter, _) = px.encode_into(px_prev, CountingWriter::new()).unwrap();
+            size += usize::MAX - counter.capacity();
+        }
+        px_prev = px;
+    }
+    size + (run + 61) / 62
 }
 
 /// Encode the image into a pre-allocated buffer.
@@ -113,10 +518,195 @@ pub fn encode_to_vec(data: impl AsRef<[u8]>, width: u32, height: u32) -> Result<
     Encoder::new(&data, width, height)?.encode_to_vec()
 }
 
+/// Encode the image into a newly allocated vector, following the reference encoder exactly.
+///
+/// See [`Encoder::encode_canonical`] for details.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[inline]
+pub fn encode_canonical(data: impl AsRef<[u8]>, width: u32, height: u32) -> Result<Vec<u8>> {
+    Encoder::new(&data, width, height)?.encode_canonical()
+}
+
+/// Encode the image and write it into a file, replacing its contents if it exists.
+#[cfg(feature = "std")]
+#[inline]
+pub fn encode_file(
+    path: impl AsRef<Path>, data: impl AsRef<[u8]>, width: u32, height: u32,
+) -> Result<()> {
+    std::fs::write(path, encode_to_vec(data, width, height)?).map_err(Into::into)
+}
+
+/// Encode a procedurally generated image, streaming it directly to a writer.
+///
+/// This combines [`Encoder::from_fn`] with [`Encoder::encode_to_stream`]: pixels are
+/// requested from `f(x, y)` in row-major order as they are encoded, so neither the input
+/// image nor the encoded output is ever held in memory. The output is written out in
+/// chunks of [`STREAM_FLUSH_CHUNK`] bytes, flushing `writer` after each chunk and once
+/// more at the end. Returns the total number of bytes written.
+#[cfg(feature = "std")]
+#[inline]
+pub fn encode_fn_to_stream<W: Write>(
+    width: u32, height: u32, channels: Channels, f: impl Fn(u32, u32) -> [u8; 4], writer: &mut W,
+) -> Result<usize> {
+    let encoder = Encoder::from_fn(width, height, channels, &f)?;
+    let mut out =
+        std::io::BufWriter::with_capacity(STREAM_FLUSH_CHUNK, FlushingWriter(&mut *writer));
+    let n_written = encoder.encode_to_stream(&mut out)?;
+    out.flush()?;
+    Ok(n_written)
+}
+
+/// Size of the chunks after which [`encode_fn_to_stream`] flushes its writer.
+#[cfg(feature = "std")]
+pub const STREAM_FLUSH_CHUNK: usize = 1 << 16;
+
+/// Flushes the inner writer after every write (meant to be wrapped in a `BufWriter`).
+#[cfg(feature = "std")]
+struct FlushingWriter<W>(W);
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for FlushingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write_all(buf)?;
+        self.0.flush()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Encode an image of the given size filled with a single color.
+///
+/// The stream is emitted directly (a single color operation followed by runs), so no
+/// input buffer has to be allocated. For `Channels::Rgb`, the alpha value is ignored.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[inline]
+pub fn encode_solid(
+    color: [u8; 4], width: u32, height: u32, channels: Channels,
+) -> Result<Vec<u8>> {
+    let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+    let n_pixels = header.n_pixels();
+    let mut out = Vec::with_capacity(QOI_HEADER_SIZE + 6 + n_pixels / 62 + QOI_PADDING_SIZE);
+    out.extend_from_slice(&header.encode(Endianness::Big));
+    match channels {
+        Channels::Rgb => encode_solid_impl(&mut out, [color[0], color[1], color[2]], n_pixels)?,
+        Channels::Rgba => encode_solid_impl(&mut out, color, n_pixels)?,
+    }
+    Ok(out)
+}
+
+/// Returns the bytes of the operation emitted for pixel `cur` following pixel `prev`.
+///
+/// The color index is assumed to be fresh, so a repeated pixel produces a single-pixel
+/// QOI_OP_RUN and only `[0, 0, 0, 0]` (which matches the zeroed slot 0) produces
+/// QOI_OP_INDEX; any other pixel is encoded via its difference from `prev` or by value.
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub fn encode_one_op(prev: [u8; 4], cur: [u8; 4]) -> Vec<u8> {
+    let mut state = EncodeState { px_prev: prev.into(), ..EncodeState::<4>::new() };
+    let mut index = [Pixel::new(); 256];
+    let mut out = Vec::with_capacity(5);
+    // can't fail, the vector grows as needed
+    let _ = state.encode_px::<_, StandardHash, true, REFERENCE>(
+        &mut index,
+        &mut out,
+        cur.into(),
+        0,
+        true,
+        &mut |_, _| (),
+    );
+    out
+}
+
+/// Encodes the pixels starting from a pre-seeded color index, appending them to `out`.
+#[cfg(any(feature = "alloc", feature = "std"))]
+fn encode_seeded<const N: usize, const REF: bool>(
+    out: &mut Vec<u8>, mut index: [Pixel<4>; 256], n_pixels: usize,
+    pixel: impl Fn(usize) -> [u8; 4],
+) -> Result<()>
+where
+    Pixel<N>: SupportedChannels,
+    [u8; N]: Pod,
+{
+    let mut state = EncodeState::<N>::new();
+    let mut buf = out;
+    for i in 0..n_pixels {
+        let mut px = [0; N];
+        px.copy_from_slice(&pixel(i)[..N]);
+        let is_last = i == n_pixels - 1;
+        buf = state.encode_px::<_, StandardHash, true, REF>(
+            &mut index,
+            buf,
+            px.into(),
+            i,
+            is_last,
+            &mut |_, _| (),
+        )?;
+    }
+    buf.write_many(&QOI_PADDING)?;
+    Ok(())
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[inline]
+#[allow(clippy::cast_possible_truncation)]
+fn encode_solid_impl<const N: usize>(out: &mut Vec<u8>, px: [u8; N], n_pixels: usize) -> Result<()>
+where
+    Pixel<N>: SupportedChannels,
+    [u8; N]: Pod,
+{
+    let mut state = EncodeState::<N>::new();
+    let mut index = [Pixel::new(); 256];
+    let is_last = n_pixels == 1;
+    let mut buf = state.encode_px::<_, StandardHash, true, REFERENCE>(
+        &mut index,
+        out,
+        px.into(),
+        0,
+        is_last,
+        &mut |_, _| (),
+    )?;
+    // the first pixel may have started a run already if it matches the initial pixel
+    let mut remaining = usize::from(state.run) + n_pixels - 1;
+    while remaining != 0 {
+        let run = remaining.min(62);
+        buf = buf.write_one(QOI_OP_RUN | (run - 1) as u8)?;
+        remaining -= run;
+    }
+    buf.write_many(&QOI_PADDING)?;
+    Ok(())
+}
+
 /// Encode QOI images into buffers or into streams.
+#[derive(Clone, Copy)]
 pub struct Encoder<'a> {
-    data: &'a [u8],
+    source: Source<'a>,
     header: Header,
+    transform: Transform,
+    endianness: Endianness,
+    ops: Ops,
+    trailer: Option<&'a [u8]>,
+}
+
+impl Debug for Encoder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+        let mut s = f.debug_struct("Encoder");
+        s.field("width", &header.width)
+            .field("height", &header.height)
+            .field("channels", &header.channels)
+            .field("colorspace", &header.colorspace);
+        // only row-major interleaved sources have a meaningful row stride
+        match self.source {
+            Source::Interleaved(_) => {
+                s.field("stride", &(header.width as usize * header.channels.bytes_per_pixel()))
+            }
+            Source::Strided { stride, .. } => s.field("stride", &stride),
+            _ => &mut s,
+        };
+        s.finish_non_exhaustive()
+    }
 }
 
 impl<'a> Encoder<'a> {
@@ -136,7 +726,187 @@ impl<'a> Encoder<'a> {
             return Err(Error::InvalidImageLength { size, width, height });
         }
         header.channels = Channels::try_from(n_channels.min(0xff) as u8)?;
-        Ok(Self { data, header })
+        Ok(Self::from_source(Source::Interleaved(data), header))
+    }
+
+    /// Creates a new encoder from a slice of RGBA pixels and image dimensions.
+    ///
+    /// The slice must contain exactly `width * height` pixels. The color space will be
+    /// set to sRGB by default.
+    #[inline]
+    pub fn from_rgba_pixels(pixels: &'a [[u8; 4]], width: u32, height: u32) -> Result<Self> {
+        Self::from_pixels(pixels, width, height, Channels::Rgba)
+    }
+
+    /// Creates a new encoder from a slice of RGB pixels and image dimensions.
+    ///
+    /// The slice must contain exactly `width * height` pixels. The color space will be
+    /// set to sRGB by default.
+    #[inline]
+    pub fn from_rgb_pixels(pixels: &'a [[u8; 3]], width: u32, height: u32) -> Result<Self> {
+        Self::from_pixels(pixels, width, height, Channels::Rgb)
+    }
+
+    /// Creates a new encoder from interleaved pixel data stored in column-major order.
+    ///
+    /// That is, the pixel at `(x, y)` starts at byte `(x * height + y) * channels`. The data
+    /// is read column-wise on the fly, which avoids transposing it into an intermediate
+    /// buffer at the cost of less cache-friendly access. The color space will be set to sRGB.
+    #[inline]
+    pub fn new_column_major(
+        data: &'a (impl AsRef<[u8]> + ?Sized), width: u32, height: u32, channels: Channels,
+    ) -> Result<Self> {
+        let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+        let (data, width, height) = (data.as_ref(), width as usize, height as usize);
+        Self::from_source(Source::ColumnMajor { data, width, height }, header).validated()
+    }
+
+    /// Creates a new encoder from interleaved pixel data whose rows start every `stride`
+    /// bytes, with the number of channels (and optionally their order) given explicitly.
+    ///
+    /// Either [`Channels`] or [`RawChannels`] can be passed in; the latter allows reading
+    /// components in a non-standard order (e.g. BGRA) or packed pixels (e.g. RGB565), which
+    /// are converted on the fly; `stride` is in bytes either way. Any
+    /// bytes between the end of a row and the start of the next one are ignored, and the
+    /// last row doesn't need to be padded. See [`Encoder::validate`] for the checks
+    /// performed on the parameters. The color space will be set to sRGB.
+    #[inline]
+    pub fn new_raw(
+        data: &'a (impl AsRef<[u8]> + ?Sized), width: u32, height: u32, stride: usize,
+        channels: impl Into<RawChannels>,
+    ) -> Result<Self> {
+        let raw = channels.into();
+        let header = Header::try_new(width, height, raw.channels(), ColorSpace::default())?;
+        let (data, width) = (data.as_ref(), width as usize);
+        Self::from_source(Source::Strided { data, width, stride, raw }, header).validated()
+    }
+
+    /// Creates a new encoder that generates each pixel on the fly by calling `f(x, y)`.
+    ///
+    /// The function returns RGBA colors; the alpha component is ignored for 3-channel
+    /// images. No input buffer is ever allocated, which is useful for procedural images.
+    /// Note that `f` is called again for each pixel every time the image is encoded. The
+    /// color space will be set to sRGB.
+    #[inline]
+    pub fn from_fn(
+        width: u32, height: u32, channels: Channels, f: &'a dyn Fn(u32, u32) -> [u8; 4],
+    ) -> Result<Self> {
+        let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+        Ok(Self::from_source(Source::Fn { f, width }, header))
+    }
+
+    /// Creates a new encoder from packed little-endian RGB565 pixels, 2 bytes per pixel.
+    ///
+    /// This is the native format of many embedded framebuffers, so they can be encoded
+    /// directly. The channels are expanded to 8 bits on the fly by replicating their most
+    /// significant bits (so that e.g. 0x1f maps to 0xff), producing a 3-channel image. This
+    /// is a shorthand for [`Encoder::new_raw`] with [`RawChannels::Rgb565`] and unpadded
+    /// rows, which should be used for padded ones. The color space will be set to sRGB.
+    #[inline]
+    pub fn from_rgb565(data: &'a [u8], width: u32, height: u32) -> Result<Self> {
+        let stride = (width as usize).saturating_mul(RawChannels::Rgb565.bytes_per_pixel());
+        Self::new_raw(data, width, height, stride, RawChannels::Rgb565)
+    }
+
+    /// Checks that the pixel data is consistent with the image dimensions, the number of
+    /// channels and the row stride (if any).
+    ///
+    /// Returns [`Error::InvalidStride`] if the stride is smaller than a row of pixels,
+    /// [`Error::InvalidImageLength`] if the data is too short or has the wrong length, and
+    /// [`Error::InvalidPaletteIndex`] if a palette index is out of bounds. All constructors
+    /// already perform these checks, so this is mainly useful for debug assertions.
+    pub fn validate(&self) -> Result<()> {
+        let (width, height) = (self.header.width, self.header.height);
+        let check_len = |size: usize, expected: usize| {
+            if size == expected {
+                Ok(())
+            } else {
+                Err(Error::InvalidImageLength { size, width, height })
+            }
+        };
+        match self.source {
+            Source::Interleaved(data) | Source::ColumnMajor { data, .. } => {
+                check_len(data.len(), self.header.n_bytes())
+            }
+            Source::Planes { r, g, b, a } => [r, g, b]
+                .into_iter()
+                .chain(a)
+                .try_for_each(|plane| check_len(plane.len(), self.header.n_pixels())),
+            Source::Indexed { indices, palette } => {
+                check_len(indices.len(), self.header.n_pixels())?;
+                match indices.iter().find(|&&i| i as usize >= palette.len()) {
+                    Some(&index) => {
+                        Err(Error::InvalidPaletteIndex { index, palette_len: palette.len() })
+                    }
+                    None => Ok(()),
+                }
+            }
+            Source::Strided { data, width: w, stride, raw } => {
+                let min_stride = w * raw.bytes_per_pixel();
+                if stride < min_stride {
+                    return Err(Error::InvalidStride { stride, min_stride });
+                }
+                let size = data.len();
+                let required =
+                    (height as usize - 1).saturating_mul(stride).saturating_add(min_stride);
+                if size < required {
+                    return Err(Error::InvalidImageLength { size, width, height });
+                }
+                Ok(())
+            }
+            Source::Fn { .. } => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn validated(self) -> Result<Self> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    #[inline]
+    const fn from_source(source: Source<'a>, header: Header) -> Self {
+        let (transform, endianness) = (Transform::new(), Endianness::Big);
+        Self { source, header, transform, endianness, ops: Ops::new(), trailer: None }
+    }
+
+    #[inline]
+    fn from_pixels<const N: usize>(
+        pixels: &'a [[u8; N]], width: u32, height: u32, channels: Channels,
+    ) -> Result<Self>
+    where
+        [u8; N]: Pod,
+    {
+        let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+        Self::from_source(Source::Interleaved(cast_slice(pixels)), header).validated()
+    }
+
+    /// Creates a new encoder from separate per-channel planes and image dimensions.
+    ///
+    /// Each plane must contain exactly `width * height` bytes. Pixels are interleaved on
+    /// the fly while encoding, so no intermediate buffer is allocated. If the alpha plane
+    /// is `None`, a 3-channel image is produced. The color space will be set to sRGB.
+    #[inline]
+    pub fn from_planes(
+        r: &'a [u8], g: &'a [u8], b: &'a [u8], a: Option<&'a [u8]>, width: u32, height: u32,
+    ) -> Result<Self> {
+        let channels = if a.is_some() { Channels::Rgba } else { Channels::Rgb };
+        let header = Header::try_new(width, height, channels, ColorSpace::default())?;
+        Self::from_source(Source::Planes { r, g, b, a }, header).validated()
+    }
+
+    /// Creates a new encoder from palette indices (one byte per pixel) and an RGBA palette.
+    ///
+    /// The indices are looked up in the palette on the fly while encoding, which produces
+    /// a 4-channel image without expanding it into an intermediate buffer. Returns
+    /// [`Error::InvalidPaletteIndex`] if any index is out of the palette bounds. The color
+    /// space will be set to sRGB.
+    #[inline]
+    pub fn from_indexed(
+        indices: &'a [u8], palette: &'a [[u8; 4]], width: u32, height: u32,
+    ) -> Result<Self> {
+        let header = Header::try_new(width, height, Channels::Rgba, ColorSpace::default())?;
+        Self::from_source(Source::Indexed { indices, palette }, header).validated()
     }
 
     /// Returns a new encoder with modified color space.
@@ -149,6 +919,124 @@ impl<'a> Encoder<'a> {
         self
     }
 
+    /// Returns a new encoder that un-premultiplies alpha before encoding.
+    ///
+    /// If enabled, the input is assumed to have premultiplied alpha, and each color
+    /// component is divided by `alpha / 255` (rounded and clamped) on the fly, so that
+    /// the encoded image has straight alpha. Fully transparent pixels are kept as is.
+    /// This has no effect on 3-channel images.
+    #[inline]
+    pub const fn with_unpremultiply(mut self, unpremultiply: bool) -> Self {
+        self.transform.unpremultiply = unpremultiply;
+        self
+    }
+
+    /// Returns a new encoder that only keeps the `bits` most significant bits of each channel.
+    ///
+    /// The remaining low bits are zeroed before encoding (for example, `6` clears the two
+    /// lowest bits), which makes runs and index hits more likely. This is lossy unless
+    /// `bits` is 8 (the default); values above 8 are treated as 8.
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn with_bit_reduction(mut self, bits: u8) -> Self {
+        let bits = if bits > 8 { 8 } else { bits };
+        self.transform.mask = (0xff_u16 << (8 - bits)) as u8;
+        self
+    }
+
+    /// Returns a new encoder that snaps alpha to either 0 or 255 before encoding.
+    ///
+    /// Alpha values below `threshold` become fully transparent, and the rest become fully
+    /// opaque (after un-premultiplying, if enabled). This is lossy on alpha only, but makes
+    /// runs and index hits more likely for images that only need 1-bit alpha, such as
+    /// anti-aliased sprites. This has no effect on 3-channel images.
+    #[inline]
+    pub const fn with_alpha_threshold(mut self, threshold: u8) -> Self {
+        self.transform.alpha_threshold = Some(threshold);
+        self
+    }
+
+    /// Returns a new encoder that appends a metadata block after the end padding.
+    ///
+    /// The block consists of a single length byte followed by the metadata itself, so the
+    /// metadata can be at most 255 bytes long; it can be read back via
+    /// [`read_trailer_metadata`](crate::read_trailer_metadata). Decoders ignore any bytes
+    /// that follow the padding, so the files remain readable by other implementations.
+    #[inline]
+    pub const fn with_trailer_metadata(mut self, metadata: &'a [u8]) -> Result<Self> {
+        if metadata.len() > QOI_TRAILER_MAX_LEN {
+            return Err(Error::TrailerTooLong { size: metadata.len() });
+        }
+        self.trailer = Some(metadata);
+        Ok(self)
+    }
+
+    /// Returns a new encoder that emits a QOI_OP_RGB or QOI_OP_RGBA operation per pixel.
+    ///
+    /// No runs, index lookups or diffs are used, so the output is still a valid image but
+    /// is as large as possible (see [`encode_max_len`]); this is mainly useful for
+    /// generating test fixtures for decoders.
+    #[inline]
+    pub const fn with_raw_ops(mut self, raw_ops: bool) -> Self {
+        self.ops.raw = raw_ops;
+        self
+    }
+
+    /// Returns a new encoder that never emits QOI_OP_INDEX operations.
+    ///
+    /// Pixels that would otherwise be encoded as index lookups fall back to the other
+    /// operations. The output is still a standard image, but it can also be read by
+    /// decoders that don't implement the color index.
+    #[inline]
+    pub const fn with_disable_index(mut self, disable_index: bool) -> Self {
+        self.ops.no_index = disable_index;
+        self
+    }
+
+    /// Returns a new encoder that follows the given variant of the encoding algorithm.
+    ///
+    /// By default, [`CompatMode::Fast`] is used, unless the `reference` feature is enabled;
+    /// this overrides the feature either way. See [`CompatMode`] for details.
+    #[inline]
+    pub const fn with_compat(mut self, compat: CompatMode) -> Self {
+        self.ops.compat = compat;
+        self
+    }
+
+    /// Returns a new encoder that writes the header dimensions in the given byte order.
+    ///
+    /// Note: the specification mandates big-endian (the default); little-endian is only
+    /// meant for producing files for non-conforming readers.
+    #[inline]
+    pub const fn with_header_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Returns an iterator lazily yielding the encoded bytes, including the header and
+    /// the padding.
+    ///
+    /// The encoder state is kept between calls, so that only a few bytes are buffered at
+    /// a time; this doesn't require `std` nor `alloc`.
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn byte_iter(&self) -> impl Iterator<Item = u8> + 'a {
+        let mut pending = ArrayBuf::new();
+        let _ = (&mut pending).write_many(&self.header.encode(self.endianness));
+        let iter = ByteIter {
+            source: self.source,
+            transform: self.transform,
+            ops: self.ops,
+            header: self.header,
+            state: EncodeState::new(),
+            index: [Pixel::new(); 256],
+            i: 0,
+            pending,
+        };
+        let trailer = self.trailer.map(|m| iter::once(m.len() as u8).chain(m.iter().copied()));
+        iter.chain(trailer.into_iter().flatten())
+    }
+
     /// Returns the inferred number of channels.
     #[inline]
     pub const fn channels(&self) -> Channels {
@@ -166,23 +1054,134 @@ impl<'a> Encoder<'a> {
     /// Can be used to pre-allocate the buffer to encode the image into.
     #[inline]
     pub fn required_buf_len(&self) -> usize {
-        self.header.encode_max_len()
+        self.header.encode_max_len() + self.trailer_len()
+    }
+
+    /// Returns the number of bytes of scratch memory used while encoding, in addition to the
+    /// input and the output.
+    ///
+    /// This is the color index (256 RGBA entries, of which only 64 are used, so that indexing
+    /// it by a byte needs no bounds checks) plus the state kept between pixels; both live on
+    /// the stack, and encoding into a buffer doesn't allocate.
+    #[inline]
+    pub const fn working_memory_bytes(&self) -> usize {
+        let state_size = match self.header.channels {
+            Channels::Rgb => mem::size_of::<EncodeState<3>>(),
+            Channels::Rgba => mem::size_of::<EncodeState<4>>(),
+        };
+        mem::size_of::<[Pixel<4>; 256]>() + state_size
+    }
+
+    /// Returns the exact number of bytes the encoded image will take.
+    ///
+    /// This performs a dry run of the encoder without writing the output anywhere.
+    #[inline]
+    pub fn encoded_len(&self) -> Result<usize> {
+        let n_written = self.encode_impl_all(CountingWriter::new())?;
+        Ok(QOI_HEADER_SIZE + n_written + self.trailer_len())
+    }
+
+    /// Collects statistics of the operations the encoder emits for this image.
+    ///
+    /// This performs a dry run of the encoder without writing the output anywhere.
+    #[inline]
+    pub fn encode_stats(&self) -> Result<EncodeStats> {
+        let mut stats = EncodeStats::default();
+        let mut run_start = None;
+        let n_written = self.encode_impl_all_traced(CountingWriter::new(), |i, kind| {
+            if let Some(start) = run_start.take() {
+                stats.add_run(i - start);
+            }
+            if kind == OpKind::Run {
+                run_start = Some(i);
+            }
+            stats.add_op(kind);
+        })?;
+        if let Some(start) = run_start {
+            stats.add_run(self.header.n_pixels() - start);
+        }
+        stats.size = QOI_HEADER_SIZE + n_written;
+        Ok(stats)
+    }
+
+    /// Checks whether the input pixels appear to be in the wrong channel order.
+    ///
+    /// This is a heuristic for a common mistake: BGR(A) data passed as RGB(A) makes most
+    /// images look unnaturally blue. A swap is suggested if blue dominates in more than half
+    /// of the pixels, and in at least twice as many pixels as red does; the transforms set up
+    /// in the encoder (if any) are not applied.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn detect_channel_order_hint(&self) -> ChannelOrderHint {
+        // by how much a channel has to exceed the opposite one to dominate a pixel
+        const MARGIN: u8 = 16;
+        let dominates = |c: u8, other: u8, green: u8| c > other.saturating_add(MARGIN) && c > green;
+        let (n_pixels, channels) = (self.header.n_pixels(), self.header.channels);
+        let mut hint = ChannelOrderHint::default();
+        let mut sums = [0_u64; 3];
+        for i in 0..n_pixels {
+            let px = self.source.pixel(i, channels);
+            for (sum, c) in sums.iter_mut().zip(px) {
+                *sum += u64::from(c);
+            }
+            hint.n_blue_dominant += usize::from(dominates(px[2], px[0], px[1]));
+            hint.n_red_dominant += usize::from(dominates(px[0], px[2], px[1]));
+        }
+        let n = n_pixels as u64;
+        hint.mean = sums.map(|sum| ((sum + n / 2) / n) as u8);
+        hint.suggest_swap =
+            hint.n_blue_dominant * 2 > n_pixels && hint.n_blue_dominant >= 2 * hint.n_red_dominant;
+        hint
+    }
+
+    /// Returns the number of encoded bytes each row of the image contributes.
+    ///
+    /// The encoder state is kept across rows, so this measures how much each row adds to the
+    /// op stream (e.g. to visualize the compression across the image); rows can't be decoded
+    /// independently. Operations that span several rows (runs) are attributed to the row
+    /// where they start; neither the header nor the padding is included.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn row_sizes(&self) -> Result<Vec<usize>> {
+        let width = self.header.width as usize;
+        let mut sizes = vec![0; self.header.height as usize];
+        self.encode_impl_all_traced(CountingWriter::new(), |i, kind| {
+            sizes[i / width] += match kind {
+                OpKind::Index | OpKind::Diff | OpKind::Run => 1,
+                OpKind::Luma => 2,
+                OpKind::Rgb => 4,
+                OpKind::Rgba => 5,
+            };
+        })?;
+        Ok(sizes)
     }
 
     /// Encodes the image to a pre-allocated buffer and returns the number of bytes written.
     ///
     /// The minimum size of the buffer can be found via [`Encoder::required_buf_len`].
     #[inline]
-    pub fn encode_to_buf(&self, mut buf: impl AsMut<[u8]>) -> Result<usize> {
+    pub fn encode_to_buf(&self, buf: impl AsMut<[u8]>) -> Result<usize> {
+        self.encode_with_trace(buf, |_, _| ())
+    }
+
+    /// Encodes the image to a pre-allocated buffer, reporting each emitted operation.
+    ///
+    /// The callback receives the index of the first pixel covered by the operation and
+    /// the operation kind, which is useful for diagnosing the compression of an image.
+    /// Returns the number of bytes written, just like [`Encoder::encode_to_buf`].
+    #[inline]
+    pub fn encode_with_trace(
+        &self, mut buf: impl AsMut<[u8]>, trace: impl FnMut(usize, OpKind),
+    ) -> Result<usize> {
         let buf = buf.as_mut();
         let size_required = self.required_buf_len();
         if unlikely(buf.len() < size_required) {
             return Err(Error::OutputBufferTooSmall { size: buf.len(), required: size_required });
         }
         let (head, tail) = buf.split_at_mut(QOI_HEADER_SIZE); // can't panic
-        head.copy_from_slice(&self.header.encode());
-        let n_written = encode_impl_all(BytesMut::new(tail), self.data, self.header.channels)?;
-        Ok(QOI_HEADER_SIZE + n_written)
+        head.copy_from_slice(&self.header.encode(self.endianness));
+        let n_written = self.encode_impl_all_traced(BytesMut::new(&mut *tail), trace)?;
+        let trailer_len = self.write_trailer(&mut tail[n_written..]);
+        Ok(QOI_HEADER_SIZE + n_written + trailer_len)
     }
 
     /// Encodes the image into a newly allocated vector of bytes and returns it.
@@ -195,16 +1194,368 @@ impl<'a> Encoder<'a> {
         Ok(out)
     }
 
+    /// Encodes the image into an existing vector, replacing its contents, and returns the
+    /// number of bytes written.
+    ///
+    /// The vector's capacity is reused, so encoding a sequence of similarly sized images into
+    /// the same vector doesn't allocate once it has grown to [`Encoder::required_buf_len`].
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn encode_into_vec(&self, out: &mut Vec<u8>) -> Result<usize> {
+        out.clear();
+        out.resize(self.required_buf_len(), 0);
+        let size = self.encode_to_buf(&mut *out)?;
+        out.truncate(size);
+        Ok(size)
+    }
+
+    /// Encodes the image into a buffer checked out from `pool` and returns it.
+    ///
+    /// The buffer is returned to the pool once the guard is dropped, so that encoding many
+    /// images concurrently doesn't allocate once the pool has warmed up.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn encode_to_vec_pooled<'p>(&self, pool: &'p BufferPool) -> Result<PooledBuffer<'p>> {
+        let mut buf = pool.checkout();
+        self.encode_into_vec(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Encodes the image into a newly allocated vector and returns it along with the offsets
+    /// of the header, the op stream and the padding within it.
+    ///
+    /// This is useful for indexing images stored in archives without parsing them again.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn encode_to_vec_with_layout(&self) -> Result<(Vec<u8>, Layout)> {
+        let out = self.encode_to_vec()?;
+        let total_len = out.len();
+        let padding_start = total_len - self.trailer_len() - QOI_PADDING_SIZE;
+        Ok((out, Layout { header_start: 0, body_start: QOI_HEADER_SIZE, padding_start, total_len }))
+    }
+
+    /// Encodes the image into a newly allocated vector, indexing colors via a custom hash.
+    ///
+    /// The header is tagged with the magic bytes of the hash, so that the image can only
+    /// be read back via [`decode_to_vec_with_hash`](crate::decode_to_vec_with_hash) with the
+    /// same hash; with [`StandardHash`](crate::StandardHash), this is the same as
+    /// [`Encoder::encode_to_vec`]. Meant for comparing the compression of different hashes.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn encode_to_vec_with_hash<H: ColorHash>(&self) -> Result<Vec<u8>> {
+        let mut out = vec![0_u8; self.required_buf_len()];
+        let (head, tail) = out.split_at_mut(QOI_HEADER_SIZE); // can't panic
+        head.copy_from_slice(&self.header.encode(self.endianness));
+        head[..4].copy_from_slice(&H::MAGIC.to_be_bytes());
+        let n_written =
+            self.encode_impl_all_hashed::<_, H>(BytesMut::new(&mut *tail), |_, _| ())?;
+        let trailer_len = self.write_trailer(&mut tail[n_written..]);
+        out.truncate(QOI_HEADER_SIZE + n_written + trailer_len);
+        Ok(out)
+    }
+
+    /// Encodes the image into a newly allocated vector, seeding the color index with the most
+    /// common colors of the first row (non-standard, experimental).
+    ///
+    /// The seeded colors are stored after the header, which is tagged with its own magic
+    /// bytes, so the image can only be read back via
+    /// [`decode_to_vec_prewarmed`](crate::decode_to_vec_prewarmed). Since the seeded colors
+    /// are stored by value, this can at best save the cost of the ops that would've produced
+    /// them otherwise. Raw ops and a disabled index are ignored here.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    pub fn encode_to_vec_prewarmed(&self) -> Result<Vec<u8>> {
+        let channels = self.header.channels;
+        let pixel = |i| self.transform.apply_rgba(self.source.pixel(i, channels), channels);
+        let mut counts = BTreeMap::new();
+        for i in 0..self.header.width as usize {
+            *counts.entry(pixel(i)).or_insert(0_usize) += 1;
+        }
+        let mut colors = counts.into_iter().collect::<Vec<_>>();
+        colors.sort_by_key(|&(_, count)| Reverse(count));
+        let mut index = [Pixel::new(); 256];
+        let mut seeds = Vec::new();
+        for (color, _) in colors {
+            let slot = &mut index[StandardHash::hash_index(color) as usize];
+            if *slot == Pixel::new() && color != [0; 4] {
+                *slot = color.into();
+                seeds.push(color);
+            }
+        }
+
+        let mut out = Vec::with_capacity(self.required_buf_len() + 1 + 4 * seeds.len());
+        out.extend_from_slice(&self.header.encode(self.endianness));
+        out[..4].copy_from_slice(&QOI_PREWARM_MAGIC.to_be_bytes());
+        #[allow(clippy::cast_possible_truncation)]
+        out.push(seeds.len() as u8);
+        out.extend(seeds.iter().flat_map(|px| &px[..channels.bytes_per_pixel()]));
+        let (n_pixels, spec) = (self.header.n_pixels(), self.ops.compat == CompatMode::Spec);
+        match (channels, spec) {
+            (Channels::Rgb, false) => encode_seeded::<3, false>(&mut out, index, n_pixels, pixel)?,
+            (Channels::Rgb, true) => encode_seeded::<3, true>(&mut out, index, n_pixels, pixel)?,
+            (Channels::Rgba, false) => encode_seeded::<4, false>(&mut out, index, n_pixels, pixel)?,
+            (Channels::Rgba, true) => encode_seeded::<4, true>(&mut out, index, n_pixels, pixel)?,
+        }
+        let start = out.len();
+        out.resize(start + self.trailer_len(), 0);
+        self.write_trailer(&mut out[start..]);
+        Ok(out)
+    }
+
+    /// Encodes the image into a newly allocated vector, following the reference encoder
+    /// algorithm exactly.
+    ///
+    /// Unlike [`Encoder::encode_to_vec`], the output never depends on the enabled crate
+    /// features (see the `reference` feature), which makes it suitable for cases where the
+    /// bytes must be reproducible, e.g. content-addressed storage. This is a shorthand for
+    /// encoding with [`CompatMode::Spec`].
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn encode_canonical(&self) -> Result<Vec<u8>> {
+        self.with_compat(CompatMode::Spec).encode_to_vec()
+    }
+
+    /// Encodes the image into a newly allocated vector if it fits into `max_bytes`.
+    ///
+    /// The exact encoded size is computed first via a dry run (see [`Encoder::encoded_len`]),
+    /// so that [`Error::SizeBudgetExceeded`] is returned before allocating anything if the
+    /// image doesn't fit the budget.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn encode_within(&self, max_bytes: usize) -> Result<Vec<u8>> {
+        let size = self.encoded_len()?;
+        if size > max_bytes {
+            return Err(Error::SizeBudgetExceeded { size, budget: max_bytes });
+        }
+        let mut out = vec![0_u8; size];
+        let (head, tail) = out.split_at_mut(QOI_HEADER_SIZE); // can't panic
+        head.copy_from_slice(&self.header.encode(self.endianness));
+        let n_written = self.encode_impl_all(BytesMut::new(&mut *tail))?;
+        self.write_trailer(&mut tail[n_written..]);
+        Ok(out)
+    }
+
+    /// Encodes the image and returns the header, the op stream and the padding separately.
+    ///
+    /// Concatenating the three parts yields the same bytes as [`Encoder::encode_to_vec`]
+    /// (the trailer metadata, if any, is not included); this is convenient for containers
+    /// that store the header apart from the image data.
+    #[cfg(any(feature = "alloc", feature = "std"))]
+    #[inline]
+    pub fn encode_parts(&self) -> Result<(Vec<u8>, Vec<u8>, [u8; QOI_PADDING_SIZE])> {
+        let mut body = vec![0_u8; self.required_buf_len() - QOI_HEADER_SIZE];
+        let n_written = self.encode_impl_all(BytesMut::new(&mut body))?;
+        body.truncate(n_written - QOI_PADDING_SIZE);
+        Ok((self.header.encode(self.endianness).to_vec(), body, QOI_PADDING))
+    }
+
     /// Encodes the image directly to a generic writer that implements [`Write`](std::io::Write).
     ///
     /// Note: while it's possible to pass a `&mut [u8]` slice here since it implements `Write`,
     /// it would more effficient to use a specialized method instead: [`Encoder::encode_to_buf`].
     #[cfg(feature = "std")]
     #[inline]
+    #[allow(clippy::cast_possible_truncation)]
     pub fn encode_to_stream<W: Write>(&self, writer: &mut W) -> Result<usize> {
-        writer.write_all(&self.header.encode())?;
-        let n_written =
-            encode_impl_all(GenericWriter::new(writer), self.data, self.header.channels)?;
-        Ok(n_written + QOI_HEADER_SIZE)
+        writer.write_all(&self.header.encode(self.endianness))?;
+        let n_written = self.encode_impl_all(GenericWriter::new(&mut *writer))?;
+        if let Some(metadata) = self.trailer {
+            writer.write_all(&[metadata.len() as u8])?;
+            writer.write_all(metadata)?;
+        }
+        Ok(n_written + QOI_HEADER_SIZE + self.trailer_len())
+    }
+
+    /// Encodes the image directly to a generic writer, failing as soon as more than `limit`
+    /// bytes would be written.
+    ///
+    /// This is meant for bounded destinations: unlike [`Encoder::encode_within`], the size
+    /// isn't computed in advance, and encoding is aborted with [`Error::SizeBudgetExceeded`]
+    /// once the limit is hit (its `size` is then the number of bytes encoded up to that point,
+    /// including the trailer). Note that the bytes within the limit have been written to
+    /// `writer` by then.
+    #[cfg(feature = "std")]
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn encode_to_stream_with_limit<W: Write>(
+        &self, writer: &mut W, limit: usize,
+    ) -> Result<usize> {
+        // the trailer is accounted for up front, so that it's never partially written
+        let n_fixed = QOI_HEADER_SIZE + self.trailer_len();
+        if unlikely(n_fixed > limit) {
+            return Err(Error::SizeBudgetExceeded { size: n_fixed, budget: limit });
+        }
+        writer.write_all(&self.header.encode(self.endianness))?;
+        let out = LimitWriter::new(GenericWriter::new(&mut *writer), n_fixed, limit);
+        let n_written = self.encode_impl_all(out)?;
+        if let Some(metadata) = self.trailer {
+            writer.write_all(&[metadata.len() as u8])?;
+            writer.write_all(metadata)?;
+        }
+        Ok(n_written + n_fixed)
+    }
+
+    #[inline]
+    fn trailer_len(&self) -> usize {
+        self.trailer.map_or(0, |m| 1 + m.len())
+    }
+
+    /// Writes the trailer metadata block (if any) and returns its length; can't panic
+    /// as long as the output fits into [`Encoder::required_buf_len`].
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn write_trailer(&self, out: &mut [u8]) -> usize {
+        if let Some(metadata) = self.trailer {
+            out[0] = metadata.len() as u8;
+            out[1..=metadata.len()].copy_from_slice(metadata);
+        }
+        self.trailer_len()
+    }
+}
+
+/// Lazy iterator over the encoded bytes, see [`Encoder::byte_iter`].
+struct ByteIter<'a> {
+    source: Source<'a>,
+    transform: Transform,
+    ops: Ops,
+    header: Header,
+    state: EncodeState<4>,
+    index: [Pixel<4>; 256],
+    i: usize,
+    pending: ArrayBuf<QOI_HEADER_SIZE>,
+}
+
+impl Iterator for ByteIter<'_> {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            if let Some(v) = self.pending.pop_front() {
+                return Some(v);
+            }
+            let (i, n_pixels) = (self.i, self.header.n_pixels());
+            self.pending.clear();
+            match i.cmp(&n_pixels) {
+                Ordering::Less => {
+                    let channels = self.header.channels;
+                    let px = self.transform.apply_rgba(self.source.pixel(i, channels), channels);
+                    let (buf, is_last) = (&mut self.pending, i == n_pixels - 1);
+                    let (index, trace) = (&mut self.index, &mut |_, _| ());
+                    if self.ops.raw {
+                        encode_raw_px(buf, &px[..self.header.channels.bytes_per_pixel()]).ok()?;
+                    } else if self.ops.no_index {
+                        let px = Pixel::from(px);
+                        self.state
+                            .encode_px::<_, StandardHash, false, REFERENCE>(
+                                index, buf, px, i, is_last, trace,
+                            )
+                            .ok()?;
+                    } else if self.ops.compat == CompatMode::Spec {
+                        let px = Pixel::from(px);
+                        self.state
+                            .encode_px::<_, StandardHash, true, true>(
+                                index, buf, px, i, is_last, trace,
+                            )
+                            .ok()?;
+                    } else {
+                        let px = Pixel::from(px);
+                        self.state
+                            .encode_px::<_, StandardHash, true, false>(
+                                index, buf, px, i, is_last, trace,
+                            )
+                            .ok()?;
+                    }
+                }
+                Ordering::Equal => {
+                    (&mut self.pending).write_many(&QOI_PADDING).ok()?;
+                }
+                Ordering::Greater => return None,
+            }
+            self.i += 1;
+        }
+    }
+}
+
+/// Incremental encoder that accepts pixels pushed one at a time.
+///
+/// This allows encoding images produced on demand without holding the entire image
+/// in memory: the encoded bytes are buffered internally and can be drained at any
+/// point via [`StreamEncoder::take_output`]. The header is emitted upon construction.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Clone)]
+pub struct StreamEncoder {
+    header: Header,
+    state: EncodeState<4>,
+    index: [Pixel<4>; 256],
+    n_pushed: usize,
+    out: Vec<u8>,
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl StreamEncoder {
+    /// Creates a new stream encoder for the given image dimensions and channels.
+    ///
+    /// The color space will be set to sRGB by default.
+    #[inline]
+    pub fn new(width: u32, height: u32, channels: Channels) -> Result<Self> {
+        Ok(Self::from_header(Header::try_new(width, height, channels, ColorSpace::default())?))
+    }
+
+    /// Creates a new stream encoder from a given header.
+    #[inline]
+    pub fn from_header(header: Header) -> Self {
+        let out = header.encode(Endianness::Big).to_vec();
+        let (state, index, n_pushed) = (EncodeState::new(), [Pixel::new(); 256], 0);
+        Self { header, state, index, n_pushed, out }
+    }
+
+    /// Returns the header that will be stored in the encoded image.
+    #[inline]
+    pub const fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Encodes the next pixel.
+    ///
+    /// For 3-channel images the alpha component is ignored. Returns an error if all the
+    /// pixels declared in the header have already been pushed.
+    #[inline]
+    pub fn push(&mut self, px: [u8; 4]) -> Result<()> {
+        let n_pixels = self.header.n_pixels();
+        if unlikely(self.n_pushed >= n_pixels) {
+            let size = (self.n_pushed + 1) * self.header.channels.bytes_per_pixel();
+            let (width, height) = (self.header.width, self.header.height);
+            return Err(Error::InvalidImageLength { size, width, height });
+        }
+        let mut px = Pixel::from(px);
+        if self.header.channels.is_rgb() {
+            px = px.with_a(0xff);
+        }
+        let (i, is_last) = (self.n_pushed, self.n_pushed == n_pixels - 1);
+        let (index, out, trace) = (&mut self.index, &mut self.out, &mut |_, _| ());
+        self.state
+            .encode_px::<_, StandardHash, true, REFERENCE>(index, out, px, i, is_last, trace)?;
+        self.n_pushed += 1;
+        Ok(())
+    }
+
+    /// Drains the bytes that have been encoded so far.
+    #[inline]
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.out)
+    }
+
+    /// Finishes encoding and returns the remaining bytes, including the padding.
+    ///
+    /// Returns an error if fewer pixels than declared in the header have been pushed.
+    #[inline]
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        if unlikely(self.n_pushed != self.header.n_pixels()) {
+            let size = self.n_pushed * self.header.channels.bytes_per_pixel();
+            let (width, height) = (self.header.width, self.header.height);
+            return Err(Error::InvalidImageLength { size, width, height });
+        }
+        self.out.extend_from_slice(&QOI_PADDING);
+        Ok(self.out)
     }
 }
@@ -1,9 +1,14 @@
 use core::convert::Infallible;
 use core::fmt::{self, Display};
 
-use crate::consts::QOI_MAGIC;
+use crate::consts::{QOI_MAGIC, QOI_TRAILER_MAX_LEN};
+use crate::header::Header;
+use crate::types::{Channels, ComponentOrder};
 
 /// Errors that can occur during encoding or decoding.
+///
+/// Both `Debug` and `Display` are implemented via `core::fmt`, so errors can be formatted
+/// in `no_std` mode without heap allocations; `std::error::Error` requires the `std` feature.
 #[derive(Debug)]
 pub enum Error {
     /// Leading 4 magic bytes don't match when decoding
@@ -16,12 +21,36 @@ pub enum Error {
     InvalidImageDimensions { width: u32, height: u32 },
     /// Image dimensions are inconsistent with image buffer length
     InvalidImageLength { size: usize, width: u32, height: u32 },
+    /// Pixel data length is not a multiple of the number of channels
+    InvalidPixelDataLength { size: usize, channels: Channels },
+    /// Row stride is smaller than the length of a row of pixels in bytes
+    InvalidStride { stride: usize, min_stride: usize },
+    /// Palette index is out of bounds of the palette
+    InvalidPaletteIndex { index: u8, palette_len: usize },
     /// Output buffer is too small to fit encoded/decoded image
     OutputBufferTooSmall { size: usize, required: usize },
     /// Input buffer ended unexpectedly before decoding was finished
     UnexpectedBufferEnd,
     /// Invalid stream end marker encountered when decoding
     InvalidPadding,
+    /// A run operation covers more pixels than there are left in the image
+    RunOverflow,
+    /// The op stream decodes to a different number of pixels than declared in the header
+    PixelCountMismatch { got: usize, expected: usize },
+    /// The number of pixels in the op stream is not a multiple of the image width
+    IndivisiblePixelCount { n_pixels: usize, width: u32 },
+    /// Requested row is past the bottom of the image
+    RowOutOfBounds { row: usize, height: u32 },
+    /// Component order doesn't have the requested number of channels
+    ComponentOrderMismatch { channels: Channels, order: ComponentOrder },
+    /// There are bytes left after the stream end marker in strict mode
+    TrailingBytes { size: usize },
+    /// Encoded image size exceeds the requested budget
+    SizeBudgetExceeded { size: usize, budget: usize },
+    /// Trailer metadata doesn't fit into a length-prefixed block
+    TrailerTooLong { size: usize },
+    /// Images can't be combined due to mismatching dimensions or number of channels
+    IncompatibleImages { first: Header, second: Header },
     #[cfg(feature = "std")]
     /// Generic I/O error from the wrapped reader/writer
     IoError(std::io::Error),
@@ -48,6 +77,16 @@ impl Display for Error {
             Self::InvalidImageLength { size, width, height } => {
                 write!(f, "invalid image length: {size} bytes for {width}x{height}")
             }
+            Self::InvalidPixelDataLength { size, channels } => {
+                let channels = channels.as_u8();
+                write!(f, "invalid pixel data length: {size} bytes for {channels} channels")
+            }
+            Self::InvalidStride { stride, min_stride } => {
+                write!(f, "invalid stride: {stride} bytes (min: {min_stride})")
+            }
+            Self::InvalidPaletteIndex { index, palette_len } => {
+                write!(f, "invalid palette index: {index} (palette length: {palette_len})")
+            }
             Self::OutputBufferTooSmall { size, required } => {
                 write!(f, "output buffer size too small: {size} (required: {required})")
             }
@@ -57,6 +96,40 @@ impl Display for Error {
             Self::InvalidPadding => {
                 write!(f, "invalid padding (stream end marker mismatch)")
             }
+            Self::RunOverflow => {
+                write!(f, "run exceeds the number of remaining pixels")
+            }
+            Self::TrailerTooLong { size } => {
+                write!(f, "trailer metadata too long: {size} bytes (max: {QOI_TRAILER_MAX_LEN})")
+            }
+            Self::PixelCountMismatch { got, expected } => {
+                write!(f, "pixel count mismatch: got {got}, expected {expected}")
+            }
+            Self::IndivisiblePixelCount { n_pixels, width } => {
+                write!(f, "pixel count {n_pixels} is not a multiple of width {width}")
+            }
+            Self::RowOutOfBounds { row, height } => {
+                write!(f, "row {row} out of bounds for image height {height}")
+            }
+            Self::ComponentOrderMismatch { channels, order } => {
+                let channels = channels.as_u8();
+                write!(f, "component order {order:?} doesn't match {channels} channels")
+            }
+            Self::TrailingBytes { size } => {
+                write!(f, "unexpected {size} trailing bytes after the stream end marker")
+            }
+            Self::SizeBudgetExceeded { size, budget } => {
+                write!(f, "encoded size exceeds budget: {size} bytes (budget: {budget})")
+            }
+            Self::IncompatibleImages { first, second } => {
+                let (c1, c2) = (first.channels.as_u8(), second.channels.as_u8());
+                write!(
+                    f,
+                    "incompatible images: {}x{} ({c1} channels) ",
+                    first.width, first.height
+                )?;
+                write!(f, "and {}x{} ({c2} channels)", second.width, second.height)
+            }
             #[cfg(feature = "std")]
             Self::IoError(ref err) => {
                 write!(f, "i/o error: {err}")
@@ -1,10 +1,17 @@
+#[cfg(feature = "decode")]
 use core::convert::TryInto;
 
+#[cfg(feature = "decode")]
 use bytemuck::cast_slice;
 
-use crate::consts::{QOI_HEADER_SIZE, QOI_MAGIC, QOI_PIXELS_MAX};
+use crate::consts::QOI_PIXELS_MAX;
+#[cfg(any(feature = "encode", feature = "decode"))]
+use crate::consts::{QOI_HEADER_SIZE, QOI_MAGIC};
+#[cfg(feature = "encode")]
 use crate::encode_max_len;
 use crate::error::{Error, Result};
+#[cfg(any(feature = "encode", feature = "decode"))]
+use crate::types::Endianness;
 use crate::types::{Channels, ColorSpace};
 use crate::utils::unlikely;
 
@@ -39,6 +46,12 @@ impl Default for Header {
 }
 
 impl Header {
+    /// Returns a builder for constructing a header field by field.
+    #[inline]
+    pub const fn builder() -> HeaderBuilder {
+        HeaderBuilder::new()
+    }
+
     /// Creates a new header and validates image dimensions.
     #[inline]
     pub const fn try_new(
@@ -65,35 +78,71 @@ impl Header {
         self
     }
 
-    /// Serializes the header into a bytes array.
+    /// Serializes the header into a bytes array, with dimensions in the given byte order.
+    #[cfg(feature = "encode")]
     #[inline]
-    pub(crate) fn encode(&self) -> [u8; QOI_HEADER_SIZE] {
+    pub(crate) fn encode(&self, endianness: Endianness) -> [u8; QOI_HEADER_SIZE] {
+        let to_bytes = match endianness {
+            Endianness::Big => u32::to_be_bytes,
+            Endianness::Little => u32::to_le_bytes,
+        };
         let mut out = [0; QOI_HEADER_SIZE];
         out[..4].copy_from_slice(&QOI_MAGIC.to_be_bytes());
-        out[4..8].copy_from_slice(&self.width.to_be_bytes());
-        out[8..12].copy_from_slice(&self.height.to_be_bytes());
+        out[4..8].copy_from_slice(&to_bytes(self.width));
+        out[8..12].copy_from_slice(&to_bytes(self.height));
         out[12] = self.channels.into();
         out[13] = self.colorspace.into();
         out
     }
 
     /// Deserializes the header from a byte array.
+    #[cfg(feature = "decode")]
     #[inline]
     pub(crate) fn decode(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::decode_with_endianness(data, Endianness::Big)
+    }
+
+    /// Deserializes the header from a byte array, with dimensions in the given byte order.
+    #[cfg(feature = "decode")]
+    #[inline]
+    pub(crate) fn decode_with_endianness(
+        data: impl AsRef<[u8]>, endianness: Endianness,
+    ) -> Result<Self> {
+        let (mut header, [channels, colorspace]) = Self::decode_raw(data, endianness)?;
+        header.channels = channels.try_into()?;
+        header.colorspace = colorspace.try_into()?;
+        Ok(header)
+    }
+
+    /// Same as [`Header::decode_with_endianness`], but returns the channels and color space
+    /// bytes as is instead of validating them. The header has 4 channels if the channels byte
+    /// is invalid, and the linear color space if the color space byte is non-zero.
+    #[cfg(feature = "decode")]
+    #[inline]
+    pub(crate) fn decode_raw(
+        data: impl AsRef<[u8]>, endianness: Endianness,
+    ) -> Result<(Self, [u8; 2])> {
+        let from_bytes = match endianness {
+            Endianness::Big => u32::from_be_bytes,
+            Endianness::Little => u32::from_le_bytes,
+        };
         let data = data.as_ref();
         if unlikely(data.len() < QOI_HEADER_SIZE) {
             return Err(Error::UnexpectedBufferEnd);
         }
         let v = cast_slice::<_, [u8; 4]>(&data[..12]);
         let magic = u32::from_be_bytes(v[0]);
-        let width = u32::from_be_bytes(v[1]);
-        let height = u32::from_be_bytes(v[2]);
-        let channels = data[12].try_into()?;
-        let colorspace = data[13].try_into()?;
+        let width = from_bytes(v[1]);
+        let height = from_bytes(v[2]);
+        let (channels, colorspace) = (data[12], data[13]);
         if unlikely(magic != QOI_MAGIC) {
             return Err(Error::InvalidMagic { magic });
         }
-        Self::try_new(width, height, channels, colorspace)
+        let channels_or_rgba = if channels == 3 { Channels::Rgb } else { Channels::Rgba };
+        let colorspace_clamped =
+            if colorspace == 0 { ColorSpace::Srgb } else { ColorSpace::Linear };
+        let header = Self::try_new(width, height, channels_or_rgba, colorspace_clamped)?;
+        Ok((header, [channels, colorspace]))
     }
 
     /// Returns a number of pixels in the image.
@@ -107,14 +156,94 @@ impl Header {
     /// This may come useful when pre-allocating a buffer to decode the image into.
     #[inline]
     pub const fn n_bytes(&self) -> usize {
-        self.n_pixels() * self.channels.as_u8() as usize
+        self.n_pixels() * self.channels.bytes_per_pixel()
+    }
+
+    /// Returns the ratio of the image width to its height.
+    #[inline]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Returns true if the image is wider than it is tall.
+    #[inline]
+    pub const fn is_landscape(&self) -> bool {
+        self.width > self.height
+    }
+
+    /// Returns true if the image is taller than it is wide.
+    #[inline]
+    pub const fn is_portrait(&self) -> bool {
+        self.width < self.height
+    }
+
+    /// Returns true if the image width and height are equal.
+    #[inline]
+    pub const fn is_square(&self) -> bool {
+        self.width == self.height
     }
 
     /// The maximum number of bytes the encoded image will take.
     ///
     /// Can be used to pre-allocate the buffer to encode the image into.
+    #[cfg(feature = "encode")]
     #[inline]
     pub fn encode_max_len(&self) -> usize {
         encode_max_len(self.width, self.height, self.channels)
     }
 }
+
+/// Builder for [`Header`] with validation performed at build time.
+///
+/// Both width and height must be set explicitly; channels default to RGB and
+/// color space defaults to sRGB.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HeaderBuilder {
+    width: u32,
+    height: u32,
+    channels: Channels,
+    colorspace: ColorSpace,
+}
+
+impl HeaderBuilder {
+    /// Creates a new builder with default channels and color space.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { width: 0, height: 0, channels: Channels::Rgb, colorspace: ColorSpace::Srgb }
+    }
+
+    /// Sets image width in pixels.
+    #[inline]
+    pub const fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets image height in pixels.
+    #[inline]
+    pub const fn height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets the number of channels.
+    #[inline]
+    pub const fn channels(mut self, channels: Channels) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Sets the color space.
+    #[inline]
+    pub const fn colorspace(mut self, colorspace: ColorSpace) -> Self {
+        self.colorspace = colorspace;
+        self
+    }
+
+    /// Validates the image dimensions and builds the header.
+    #[inline]
+    pub const fn build(self) -> Result<Header> {
+        Header::try_new(self.width, self.height, self.channels, self.colorspace)
+    }
+}
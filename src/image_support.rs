@@ -0,0 +1,20 @@
+use image::RgbaImage;
+
+use crate::decode::Decoder;
+use crate::error::Result;
+use crate::types::Channels;
+
+/// Decode the image into an existing [`RgbaImage`], reusing its buffer if possible.
+///
+/// The image is only reallocated if its dimensions differ from those in the header, so
+/// decoding a sequence of same-sized frames into the same image doesn't allocate. The
+/// image is always decoded as RGBA (with alpha set to 255 for 3-channel images).
+pub fn decode_into_rgba_image(data: impl AsRef<[u8]>, img: &mut RgbaImage) -> Result<()> {
+    let mut decoder = Decoder::new(&data)?.with_channels(Channels::Rgba);
+    let header = *decoder.header();
+    if img.dimensions() != (header.width, header.height) {
+        *img = RgbaImage::new(header.width, header.height);
+    }
+    decoder.decode_to_buf(&mut **img)?;
+    Ok(())
+}
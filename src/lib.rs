@@ -2,7 +2,7 @@
 //!
 //! - One of the [fastest](#benchmarks) QOI encoders/decoders out there.
 //! - Compliant with the [latest](https://qoiformat.org/qoi-specification.pdf) QOI format specification.
-//! - Zero unsafe code.
+//! - Zero unsafe code (except for the optional memory-mapped and uninitialized-buffer decoding).
 //! - Supports decoding from / encoding to `std::io` streams directly.
 //! - `no_std` support.
 //! - Roundtrip-tested vs the reference C implementation; fuzz-tested.
@@ -52,8 +52,27 @@
 //! In that case anything related to `std::io`, `std::error::Error` and heap
 //! allocations is disabled. There is an additional `alloc` feature that can
 //! be activated to bring back the support for heap allocations.
+//!
+//! ### Encoder / decoder
+//!
+//! The encoder and the decoder are gated behind the `encode` and `decode` features
+//! respectively (both enabled by default), so that one-directional builds don't link
+//! the unused half. Note that these need to be re-enabled explicitly when deactivating
+//! the `default-features`.
+//!
+//! ### Memory-mapped files
+//!
+//! The optional `memmap2` feature adds [`decode_mmap`] which decodes an image straight
+//! from a memory-mapped file. This is one of the two places in the crate that use unsafe code.
+//!
+//! ### Uninitialized buffers
+//!
+//! The optional `uninit` feature adds [`Decoder::decode_to_uninit`] which decodes into an
+//! uninitialized buffer, avoiding the cost of zeroing it first; this is the other place
+//! that uses unsafe code, relying on the fact that the decoder never reads its output.
 
-#![forbid(unsafe_code)]
+#![cfg_attr(not(any(feature = "memmap2", feature = "uninit")), forbid(unsafe_code))]
+#![cfg_attr(any(feature = "memmap2", feature = "uninit"), deny(unsafe_code))]
 #![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
 #![allow(
     clippy::inline_always,
@@ -71,25 +90,91 @@ extern crate alloc;
 #[cfg(any(feature = "std", test))]
 extern crate std as alloc;
 
+#[cfg(all(feature = "encode", feature = "decode", any(feature = "alloc", feature = "std")))]
+mod compose;
+#[cfg(feature = "decode")]
 mod decode;
+#[cfg(feature = "encode")]
 mod encode;
 mod error;
 mod header;
+#[cfg(feature = "image")]
+mod image_support;
+#[cfg(feature = "ndarray")]
+mod ndarray_support;
+#[cfg(any(feature = "encode", feature = "decode"))]
 mod pixel;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "rayon")]
+mod rayon_support;
+mod stats;
 mod types;
 mod utils;
 
 #[doc(hidden)]
 pub mod consts;
 
-#[cfg(any(feature = "alloc", feature = "std"))]
-pub use crate::decode::decode_to_vec;
-pub use crate::decode::{decode_header, decode_to_buf, Decoder};
+#[cfg(all(feature = "encode", feature = "decode", any(feature = "alloc", feature = "std")))]
+pub use crate::compose::{
+    concat_horizontal, decode_progressive_level, decode_sequence_dedup, encode_progressive,
+    encode_sequence_dedup, reencode_banded, rotate, stack_vertical, transcode_add_alpha,
+};
 
-#[cfg(any(feature = "alloc", feature = "std"))]
-pub use crate::encode::encode_to_vec;
-pub use crate::encode::{encode_max_len, encode_to_buf, Encoder};
+#[cfg(all(feature = "decode", feature = "std"))]
+pub use crate::decode::decode_file;
+#[cfg(feature = "memmap2")]
+pub use crate::decode::decode_mmap;
+#[cfg(feature = "decode")]
+pub use crate::decode::{
+    count_ops, decode_header, decode_to_buf, decode_with_context, op_length, read_trailer_metadata,
+    DecodeContext, Decoder,
+};
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+pub use crate::decode::{
+    decode_banded, decode_scanline, decode_to_vec, decode_to_vec_prewarmed,
+    decode_to_vec_with_hash, repair_dimensions, DecodeResult, ProgressiveDecoder,
+};
+
+#[cfg(all(feature = "encode", any(feature = "alloc", feature = "std")))]
+pub use crate::encode::{
+    encode_canonical, encode_one_op, encode_solid, encode_to_vec, StreamEncoder,
+};
+#[cfg(all(feature = "encode", feature = "std"))]
+pub use crate::encode::{encode_file, encode_fn_to_stream, STREAM_FLUSH_CHUNK};
+#[cfg(feature = "encode")]
+pub use crate::encode::{encode_max_len, encode_to_buf, theoretical_min_size, Encoder};
 
 pub use crate::error::{Error, Result};
-pub use crate::header::Header;
-pub use crate::types::{Channels, ColorSpace};
+pub use crate::header::{Header, HeaderBuilder};
+#[cfg(feature = "image")]
+pub use crate::image_support::decode_into_rgba_image;
+#[cfg(feature = "ndarray")]
+pub use crate::ndarray_support::{decode_to_ndarray, encode_from_ndarray};
+#[cfg(any(feature = "encode", feature = "decode"))]
+pub use crate::pixel::{index_slot, ColorHash, StandardHash, WeightedHash};
+#[cfg(feature = "std")]
+pub use crate::pool::{BufferPool, PooledBuffer};
+#[cfg(feature = "rayon")]
+pub use crate::rayon_support::{
+    decode_and_par_map, decode_archive_parallel, decode_banded_parallel,
+};
+#[cfg(all(feature = "decode", feature = "std"))]
+pub use crate::stats::entropy_estimate;
+#[cfg(feature = "std")]
+pub use crate::stats::psnr;
+pub use crate::stats::ssim;
+#[cfg(feature = "decode")]
+pub use crate::stats::{average_color, decode_opaque_bounds, decode_phash};
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+pub use crate::stats::{count_unique_colors, count_unique_colors_capped, diff_images};
+#[cfg(feature = "encode")]
+pub use crate::stats::{ChannelOrderHint, EncodeStats};
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use crate::types::maybe_convert_channels;
+#[cfg(all(feature = "encode", any(feature = "alloc", feature = "std")))]
+pub use crate::types::Layout;
+pub use crate::types::{
+    swizzle_in_place, try_cast_pixels, Channels, ColorSpace, CompatMode, Component, ComponentOrder,
+    Endianness, OpKind, RawChannels, Rotation,
+};
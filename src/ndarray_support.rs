@@ -0,0 +1,44 @@
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use ndarray::{Array3, ArrayView3};
+
+use crate::decode::Decoder;
+use crate::encode::Encoder;
+use crate::error::{Error, Result};
+use crate::header::Header;
+use crate::types::{Channels, ColorSpace};
+
+/// Decode the image into a newly allocated array of shape `[height, width, channels]`.
+pub fn decode_to_ndarray(data: impl AsRef<[u8]>) -> Result<(Header, Array3<u8>)> {
+    let mut decoder = Decoder::new(&data)?;
+    let header = *decoder.header();
+    let shape = (header.height as usize, header.width as usize, header.channels.bytes_per_pixel());
+    let out = decoder.decode_to_vec()?;
+    let size = out.len();
+    let array = Array3::from_shape_vec(shape, out).map_err(|_| Error::InvalidImageLength {
+        size,
+        width: header.width,
+        height: header.height,
+    })?;
+    Ok((header, array))
+}
+
+/// Encode an array of shape `[height, width, channels]` into a newly allocated vector.
+///
+/// The number of channels (the last axis) must be 3 or 4. If the array is not in standard
+/// (row-major, contiguous) layout, it is copied into one before encoding.
+#[allow(clippy::cast_possible_truncation)]
+pub fn encode_from_ndarray(array: &ArrayView3<u8>, colorspace: ColorSpace) -> Result<Vec<u8>> {
+    let (height, width, channels) = array.dim();
+    let channels = Channels::try_from(channels.min(0xff) as u8)?;
+    let (width, height) = match (u32::try_from(width), u32::try_from(height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => return Err(Error::InvalidImageDimensions { width: u32::MAX, height: u32::MAX }),
+    };
+    Header::try_new(width, height, channels, colorspace)?;
+    let data = array.as_standard_layout();
+    let size = data.len();
+    let data = data.as_slice().ok_or(Error::InvalidImageLength { size, width, height })?;
+    Encoder::new(data, width, height)?.with_colorspace(colorspace).encode_to_vec()
+}
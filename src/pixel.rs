@@ -1,8 +1,15 @@
+#[cfg(feature = "encode")]
 use crate::consts::{QOI_OP_DIFF, QOI_OP_LUMA, QOI_OP_RGB, QOI_OP_RGBA};
+#[cfg(feature = "encode")]
 use crate::error::Result;
+#[cfg(feature = "encode")]
+use crate::types::OpKind;
+#[cfg(feature = "encode")]
 use crate::utils::Writer;
 use bytemuck::{cast, Pod};
 
+use crate::consts::QOI_MAGIC;
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(transparent)]
 pub struct Pixel<const N: usize>([u8; N]);
@@ -139,8 +146,9 @@ impl<const N: usize> Pixel<N> {
         self.0[2] = self.0[2].wrapping_add(b);
     }
 
+    #[cfg(feature = "encode")]
     #[inline]
-    pub fn encode_into<W: Writer>(&self, px_prev: Self, buf: W) -> Result<W> {
+    pub fn encode_into<W: Writer>(&self, px_prev: Self, buf: W) -> Result<(W, OpKind)> {
         if N == 3 || self.a_or(0) == px_prev.a_or(0) {
             let vg = self.g().wrapping_sub(px_prev.g());
             let vg_32 = vg.wrapping_add(32);
@@ -152,24 +160,35 @@ impl<const N: usize> Pixel<N> {
                 let (vr_2, vg_2, vb_2) =
                     (vr.wrapping_add(2), vg.wrapping_add(2), vb.wrapping_add(2));
                 if vr_2 | vg_2 | vb_2 | 3 == 3 {
-                    buf.write_one(QOI_OP_DIFF | vr_2 << 4 | vg_2 << 2 | vb_2)
+                    Ok((buf.write_one(QOI_OP_DIFF | vr_2 << 4 | vg_2 << 2 | vb_2)?, OpKind::Diff))
                 } else {
                     let (vg_r_8, vg_b_8) = (vg_r.wrapping_add(8), vg_b.wrapping_add(8));
                     if vg_r_8 | vg_b_8 | 15 == 15 {
-                        buf.write_many(&[QOI_OP_LUMA | vg_32, vg_r_8 << 4 | vg_b_8])
+                        let buf = buf.write_many(&[QOI_OP_LUMA | vg_32, vg_r_8 << 4 | vg_b_8])?;
+                        Ok((buf, OpKind::Luma))
                     } else {
-                        buf.write_many(&[QOI_OP_RGB, self.r(), self.g(), self.b()])
+                        let buf = buf.write_many(&[QOI_OP_RGB, self.r(), self.g(), self.b()])?;
+                        Ok((buf, OpKind::Rgb))
                     }
                 }
             } else {
-                buf.write_many(&[QOI_OP_RGB, self.r(), self.g(), self.b()])
+                Ok((buf.write_many(&[QOI_OP_RGB, self.r(), self.g(), self.b()])?, OpKind::Rgb))
             }
         } else {
-            buf.write_many(&[QOI_OP_RGBA, self.r(), self.g(), self.b(), self.a_or(0xff)])
+            let buf =
+                buf.write_many(&[QOI_OP_RGBA, self.r(), self.g(), self.b(), self.a_or(0xff)])?;
+            Ok((buf, OpKind::Rgba))
         }
     }
 }
 
+impl<const N: usize> From<[u8; N]> for Pixel<N> {
+    #[inline(always)]
+    fn from(px: [u8; N]) -> Self {
+        Self(px)
+    }
+}
+
 impl<const N: usize> From<Pixel<N>> for [u8; N] {
     #[inline(always)]
     fn from(px: Pixel<N>) -> Self {
@@ -177,6 +196,62 @@ impl<const N: usize> From<Pixel<N>> for [u8; N] {
     }
 }
 
+/// Returns the position of an RGBA color in the 64-entry color index.
+///
+/// This is the canonical hash from the QOI specification,
+/// `(r * 3 + g * 5 + b * 7 + a * 11) % 64`, as used by both the encoder
+/// and the decoder (for `QOI_OP_INDEX` operations).
+#[inline]
+pub fn index_slot(rgba: [u8; 4]) -> u8 {
+    Pixel::from(rgba).hash_index()
+}
+
+/// Hash function mapping colors to the slots of the 64-entry color index.
+///
+/// Images encoded with a non-standard hash can't be read by other decoders, so they're
+/// tagged with their own magic bytes; this is mainly meant for compression experiments,
+/// see [`Encoder::encode_to_vec_with_hash`](crate::Encoder::encode_to_vec_with_hash) and
+/// [`decode_to_vec_with_hash`](crate::decode_to_vec_with_hash).
+pub trait ColorHash {
+    /// Magic bytes identifying images encoded with this hash.
+    const MAGIC: u32;
+
+    /// Returns the index slot of an RGBA color; only the lower 6 bits are used.
+    fn hash_index(rgba: [u8; 4]) -> u8;
+}
+
+/// The hash from the QOI specification, see [`index_slot`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StandardHash;
+
+impl ColorHash for StandardHash {
+    const MAGIC: u32 = QOI_MAGIC;
+
+    #[inline(always)]
+    fn hash_index(rgba: [u8; 4]) -> u8 {
+        Pixel::from(rgba).hash_index()
+    }
+}
+
+/// Hash with custom weights, `(r * R + g * G + b * B + a * A) % 64`.
+///
+/// Images are tagged with the `qoiw` magic bytes regardless of the weights, so the same
+/// weights must be used for decoding.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct WeightedHash<const R: u8, const G: u8, const B: u8, const A: u8>;
+
+impl<const R: u8, const G: u8, const B: u8, const A: u8> ColorHash for WeightedHash<R, G, B, A> {
+    const MAGIC: u32 = u32::from_be_bytes(*b"qoiw");
+
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn hash_index(rgba: [u8; 4]) -> u8 {
+        let [r, g, b, a] = rgba.map(u32::from);
+        let weights = [R, G, B, A].map(u32::from);
+        ((r * weights[0] + g * weights[1] + b * weights[2] + a * weights[3]) % 64) as u8
+    }
+}
+
 pub trait SupportedChannels {}
 
 impl SupportedChannels for Pixel<3> {}
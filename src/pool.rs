@@ -0,0 +1,102 @@
+use core::fmt::{self, Debug};
+use core::ops::{Deref, DerefMut};
+use std::sync::{Mutex, PoisonError};
+
+/// A thread-safe pool of reusable byte buffers.
+///
+/// Buffers are checked out via [`BufferPool::checkout`] (or implicitly by
+/// [`Encoder::encode_to_vec_pooled`](crate::Encoder::encode_to_vec_pooled)) and are
+/// returned to the pool automatically when the [`PooledBuffer`] guard is dropped, so
+/// their capacity is reused by subsequent checkouts instead of being reallocated.
+#[derive(Default)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    /// Creates a new empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks out a buffer from the pool, allocating a new empty one if none is available.
+    ///
+    /// The returned buffer is always empty but may have non-zero capacity.
+    pub fn checkout(&self) -> PooledBuffer<'_> {
+        let mut buf = self.lock().pop().unwrap_or_default();
+        buf.clear();
+        PooledBuffer { buf, pool: self }
+    }
+
+    /// Returns the number of idle buffers currently held by the pool.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if the pool holds no idle buffers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        // a panic while holding the lock can't leave the list of buffers in a broken state
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool").field("len", &self.len()).finish()
+    }
+}
+
+/// A buffer checked out from a [`BufferPool`], returned to the pool when dropped.
+pub struct PooledBuffer<'a> {
+    buf: Vec<u8>,
+    pool: &'a BufferPool,
+}
+
+impl PooledBuffer<'_> {
+    /// Detaches the buffer from the pool and returns it; it won't be returned to the pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        core::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = Vec<u8>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledBuffer<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Debug for PooledBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledBuffer").field("len", &self.buf.len()).finish()
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        let buf = core::mem::take(&mut self.buf);
+        if buf.capacity() != 0 {
+            self.pool.lock().push(buf);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use core::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::decode::{decode_band_layout, decode_to_vec, Decoder};
+use crate::error::{Error, Result};
+use crate::header::Header;
+
+/// Decode the image into a newly allocated vector, then apply `f` to each pixel in parallel.
+///
+/// Decoding itself is inherently sequential, so only the post-processing is parallelized:
+/// `f` receives each decoded pixel (3 or 4 bytes, as per the header) and may modify it
+/// in place.
+pub fn decode_and_par_map<F>(data: impl AsRef<[u8]>, f: F) -> Result<(Header, Vec<u8>)>
+where
+    F: Fn(&mut [u8]) + Sync + Send,
+{
+    let mut decoder = Decoder::new(&data)?;
+    let header = *decoder.header();
+    let mut out = decoder.decode_to_vec()?;
+    out.par_chunks_exact_mut(header.channels.bytes_per_pixel()).for_each(f);
+    Ok((header, out))
+}
+
+/// Decode multiple independent images stored in a single buffer, in parallel.
+///
+/// Each range in `offsets` must span exactly one encoded image within `archive`, e.g. as
+/// recorded via [`Layout`](crate::Layout) when building the archive. The images are returned
+/// in the same order as the ranges. If any of them fails to decode, one of the errors is
+/// returned; ranges that are out of bounds result in [`Error::UnexpectedBufferEnd`].
+pub fn decode_archive_parallel(
+    archive: &[u8], offsets: &[Range<usize>],
+) -> Result<Vec<(Header, Vec<u8>)>> {
+    offsets
+        .par_iter()
+        .map(|range| decode_to_vec(archive.get(range.clone()).ok_or(Error::UnexpectedBufferEnd)?))
+        .collect()
+}
+
+/// Decodes a banded container produced by [`reencode_banded`](crate::reencode_banded),
+/// decoding all of its bands in parallel directly into the output buffer.
+pub fn decode_banded_parallel(data: &[u8]) -> Result<(Header, Vec<u8>)> {
+    let (header, rows_per_band, ranges) = decode_band_layout(data)?;
+    let mut out = vec![0; header.n_bytes()];
+    let band_len = rows_per_band * header.width as usize * header.channels.bytes_per_pixel();
+    out.par_chunks_mut(band_len).zip(ranges).try_for_each(|(band, range)| {
+        Decoder::new(&data[range])?.decode_to_buf(band).map(|_| ())
+    })?;
+    Ok((header, out))
+}
@@ -0,0 +1,309 @@
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+use alloc::collections::BTreeSet;
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+use core::ops::ControlFlow;
+
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+use bytemuck::cast_slice;
+
+#[cfg(all(feature = "decode", feature = "std"))]
+use crate::consts::QOI_HEADER_SIZE;
+#[cfg(feature = "decode")]
+use crate::decode::walk_pixels;
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+use crate::decode::{try_walk_pixels, Decoder};
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+use crate::error::Error;
+#[cfg(feature = "decode")]
+use crate::error::Result;
+#[cfg(feature = "decode")]
+use crate::header::Header;
+use crate::types::Channels;
+#[cfg(feature = "encode")]
+use crate::types::OpKind;
+
+/// Statistics of the operations emitted while encoding an image.
+///
+/// Can be obtained via [`Encoder::encode_stats`](crate::Encoder::encode_stats).
+#[cfg(feature = "encode")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EncodeStats {
+    /// Total encoded size in bytes, including the header and the padding
+    pub size: usize,
+    /// Number of `QOI_OP_INDEX` operations
+    pub num_index: usize,
+    /// Number of `QOI_OP_DIFF` operations
+    pub num_diff: usize,
+    /// Number of `QOI_OP_LUMA` operations
+    pub num_luma: usize,
+    /// Number of `QOI_OP_RUN` operations
+    pub num_run: usize,
+    /// Number of `QOI_OP_RGB` operations
+    pub num_rgb: usize,
+    /// Number of `QOI_OP_RGBA` operations
+    pub num_rgba: usize,
+    /// Longest run length covered by a single `QOI_OP_RUN` operation
+    pub max_run: usize,
+    /// Number of runs that hit the maximum run length (62 pixels)
+    pub num_runs_at_cap: usize,
+}
+
+#[cfg(feature = "encode")]
+impl EncodeStats {
+    #[inline]
+    pub(crate) fn add_op(&mut self, kind: OpKind) {
+        *match kind {
+            OpKind::Index => &mut self.num_index,
+            OpKind::Diff => &mut self.num_diff,
+            OpKind::Luma => &mut self.num_luma,
+            OpKind::Run => &mut self.num_run,
+            OpKind::Rgb => &mut self.num_rgb,
+            OpKind::Rgba => &mut self.num_rgba,
+        } += 1;
+    }
+
+    #[inline]
+    pub(crate) fn add_run(&mut self, run: usize) {
+        self.max_run = self.max_run.max(run);
+        self.num_runs_at_cap += usize::from(run == 62);
+    }
+}
+
+/// Heuristic diagnostic of the channel order of the input pixels.
+///
+/// Can be obtained via [`Encoder::detect_channel_order_hint`](crate::Encoder::detect_channel_order_hint).
+#[cfg(feature = "encode")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChannelOrderHint {
+    /// Average value of each of the red, green and blue channels
+    pub mean: [u8; 3],
+    /// Number of pixels where blue clearly dominates both red and green
+    pub n_blue_dominant: usize,
+    /// Number of pixels where red clearly dominates both green and blue
+    pub n_red_dominant: usize,
+    /// Whether the input looks like BGR(A) passed as RGB(A), i.e. most of it is blue
+    pub suggest_swap: bool,
+}
+
+/// Computes the average color of an encoded image without decoding it into a buffer.
+///
+/// Each pixel contributes equally, so runs are weighted by their length. For images
+/// without an alpha channel, the alpha component of the result is 255.
+#[cfg(feature = "decode")]
+#[allow(clippy::cast_possible_truncation)]
+pub fn average_color(data: impl AsRef<[u8]>) -> Result<[u8; 4]> {
+    let mut sums = [0_u64; 4];
+    let (header, _) = walk_pixels(data.as_ref(), |px, count| {
+        for (sum, c) in sums.iter_mut().zip(px) {
+            *sum += u64::from(c) * count as u64;
+        }
+    })?;
+    let n = header.n_pixels() as u64;
+    Ok(sums.map(|sum| ((sum + n / 2) / n) as u8))
+}
+
+/// Computes a 64-bit perceptual fingerprint (average hash) of an encoded image without
+/// decoding it into a buffer.
+///
+/// The image is box-filtered down to 8x8 grayscale cells (images smaller than that are
+/// upscaled), and bit `8 * y + x` of the result is set if cell `(x, y)` is brighter than the
+/// mean of all cells. Similar images have hashes with a small Hamming distance.
+#[cfg(feature = "decode")]
+pub fn decode_phash(bytes: &[u8]) -> Result<u64> {
+    let header = Header::decode(bytes)?;
+    let (width, height) = (header.width as usize, header.height as usize);
+    // each pixel covers cells `start..end` along each axis, at least one of them
+    let cells = |i: usize, n: usize| i * 8 / n..((i + 1) * 8 / n).max(i * 8 / n + 1);
+    let (mut sums, mut counts) = ([0_u64; 64], [0_u64; 64]);
+    let mut i = 0;
+    walk_pixels(bytes, |[r, g, b, _], count| {
+        // BT.601 luma, scaled by 1000
+        let gray = 299 * u64::from(r) + 587 * u64::from(g) + 114 * u64::from(b);
+        for j in i..i + count {
+            for cy in cells(j / width, height) {
+                for cx in cells(j % width, width) {
+                    sums[cy * 8 + cx] += gray;
+                    counts[cy * 8 + cx] += 1;
+                }
+            }
+        }
+        i += count;
+    })?;
+    let mut cells = [0_u64; 64];
+    for (cell, (sum, count)) in cells.iter_mut().zip(sums.iter().zip(counts)) {
+        *cell = sum / count;
+    }
+    let total: u64 = cells.iter().sum();
+    Ok(cells.iter().enumerate().filter(|(_, &c)| c * 64 > total).fold(0, |h, (i, _)| h | 1 << i))
+}
+
+/// Finds the tight bounding box of the pixels with a non-zero alpha without decoding the
+/// image into a buffer.
+///
+/// Returns `(min_x, min_y, max_x, max_y)` (inclusive), or `None` if the image is fully
+/// transparent; images without an alpha channel always span their full bounds.
+#[cfg(feature = "decode")]
+#[allow(clippy::cast_possible_truncation)]
+pub fn decode_opaque_bounds(bytes: &[u8]) -> Result<Option<(u32, u32, u32, u32)>> {
+    let width = Header::decode(bytes)?.width as usize;
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    let mut i = 0;
+    walk_pixels(bytes, |px, count| {
+        if px[3] != 0 {
+            let (first, last) = (i, i + count - 1);
+            let (y0, y1) = (first / width, last / width);
+            // a run that wraps to the next row covers the whole row width
+            let (x0, x1) = if y0 == y1 { (first % width, last % width) } else { (0, width - 1) };
+            bounds = Some(bounds.map_or((x0, y0, x1, y1), |(a, b, c, d)| {
+                (a.min(x0), b.min(y0), c.max(x1), d.max(y1))
+            }));
+        }
+        i += count;
+    })?;
+    Ok(bounds.map(|(x0, y0, x1, y1)| (x0 as u32, y0 as u32, x1 as u32, y1 as u32)))
+}
+
+/// Counts the distinct RGBA colors of an encoded image without decoding it into a buffer.
+///
+/// For images without an alpha channel, all colors are treated as opaque.
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+#[inline]
+pub fn count_unique_colors(data: impl AsRef<[u8]>) -> Result<usize> {
+    count_unique_colors_capped(data, usize::MAX)
+}
+
+/// Same as [`count_unique_colors`], but stops as soon as more than `cap` distinct colors
+/// are found, in which case `cap + 1` is returned.
+///
+/// This is useful for checking whether an image fits into a palette without tracking all
+/// of its colors; note that the image is not validated past the point where it stops.
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+pub fn count_unique_colors_capped(data: impl AsRef<[u8]>, cap: usize) -> Result<usize> {
+    let mut colors = BTreeSet::new();
+    let res = try_walk_pixels(data.as_ref(), |px, _| {
+        if colors.insert(px) && colors.len() > cap {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    Ok(if res.is_break() { cap.saturating_add(1) } else { colors.len() })
+}
+
+/// Finds the first pixel at which two encoded images differ.
+///
+/// Returns the index of the pixel (in row-major order) along with its RGBA colors in both
+/// images, or `None` if the images are identical; the encoding itself may differ. Returns
+/// [`Error::IncompatibleImages`] if the dimensions or the numbers of channels differ.
+#[cfg(all(feature = "decode", any(feature = "alloc", feature = "std")))]
+#[allow(clippy::type_complexity)]
+pub fn diff_images(a: &[u8], b: &[u8]) -> Result<Option<(usize, [u8; 4], [u8; 4])>> {
+    let (a, b) = (Decoder::new(a)?, Decoder::new(b)?);
+    let (first, second) = (*a.header(), *b.header());
+    if (first.width, first.height, first.channels) != (second.width, second.height, second.channels)
+    {
+        return Err(Error::IncompatibleImages { first, second });
+    }
+    let a = a.with_channels(Channels::Rgba).decode_to_vec()?;
+    let b = b.with_channels(Channels::Rgba).decode_to_vec()?;
+    let (a, b) = (cast_slice::<_, [u8; 4]>(&a), cast_slice::<_, [u8; 4]>(&b));
+    Ok(a.iter().zip(b).position(|(pa, pb)| pa != pb).map(|i| (i, a[i], b[i])))
+}
+
+/// Computes the Shannon entropy of the operations stream of an encoded image, in bits per byte.
+///
+/// The header and the padding are excluded; the result is in the range `[0, 8]`.
+#[cfg(all(feature = "decode", feature = "std"))]
+#[allow(clippy::cast_precision_loss)]
+pub fn entropy_estimate(data: &[u8]) -> Result<f64> {
+    let (_, n_bytes) = walk_pixels(data, |_, _| ())?;
+    let mut counts = [0_usize; 256];
+    for &b in &data[QOI_HEADER_SIZE..][..n_bytes] {
+        counts[b as usize] += 1;
+    }
+    let total = n_bytes as f64;
+    Ok(counts.iter().filter(|&&c| c != 0).map(|&c| c as f64 / total).map(|p| -p * p.log2()).sum())
+}
+
+/// Sums of the components of a single channel and of their products, used to compute
+/// image quality metrics.
+#[derive(Default)]
+struct ChannelSums {
+    x: f64,
+    y: f64,
+    xx: f64,
+    yy: f64,
+    xy: f64,
+    squared_error: f64,
+}
+
+/// Accumulates [`ChannelSums`] for each channel of two same-sized decoded buffers.
+fn channel_sums(original: &[u8], decoded: &[u8], channels: Channels) -> ([ChannelSums; 4], f64) {
+    let n = channels.bytes_per_pixel();
+    assert_eq!(original.len(), decoded.len(), "buffers must have the same length");
+    assert_eq!(original.len() % n, 0, "buffer length must be a multiple of {n} channels");
+    let mut sums: [ChannelSums; 4] = Default::default();
+    for (a, b) in original.chunks_exact(n).zip(decoded.chunks_exact(n)) {
+        for (s, (&a, &b)) in sums.iter_mut().zip(a.iter().zip(b)) {
+            let (a, b) = (f64::from(a), f64::from(b));
+            s.x += a;
+            s.y += b;
+            s.xx += a * a;
+            s.yy += b * b;
+            s.xy += a * b;
+            s.squared_error += (a - b) * (a - b);
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    (sums, (original.len() / n) as f64)
+}
+
+/// Computes the peak signal-to-noise ratio between two decoded buffers, in decibels.
+///
+/// All components (including alpha, if present) contribute equally to the mean squared
+/// error. Identical buffers (including empty ones) yield infinity.
+///
+/// # Panics
+///
+/// Panics if the buffers have different lengths, or if the length isn't a multiple of the
+/// number of channels.
+#[cfg(feature = "std")]
+pub fn psnr(original: &[u8], decoded: &[u8], channels: Channels) -> f64 {
+    let (sums, n_pixels) = channel_sums(original, decoded, channels);
+    let n = n_pixels * f64::from(channels.as_u8());
+    let squared_error: f64 = sums.iter().map(|s| s.squared_error).sum();
+    if squared_error == 0. {
+        return f64::INFINITY;
+    }
+    10. * (255. * 255. * n / squared_error).log10()
+}
+
+/// Computes the structural similarity index between two decoded buffers.
+///
+/// This is a simple global version: the statistics are computed over the entire image
+/// rather than in sliding windows, separately for each channel, and then averaged. The
+/// result is 1 for identical buffers (including empty ones) and lower for dissimilar ones.
+///
+/// # Panics
+///
+/// Panics if the buffers have different lengths, or if the length isn't a multiple of the
+/// number of channels.
+#[allow(clippy::suboptimal_flops)] // `mul_add` requires std
+pub fn ssim(original: &[u8], decoded: &[u8], channels: Channels) -> f64 {
+    const C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+    const C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+    let (sums, n) = channel_sums(original, decoded, channels);
+    if n == 0. {
+        return 1.;
+    }
+    let n_channels = channels.bytes_per_pixel();
+    let total: f64 = sums[..n_channels]
+        .iter()
+        .map(|s| {
+            let (mx, my) = (s.x / n, s.y / n);
+            let (vx, vy, cov) = (s.xx / n - mx * mx, s.yy / n - my * my, s.xy / n - mx * my);
+            ((2. * mx * my + C1) * (2. * cov + C2)) / ((mx * mx + my * my + C1) * (vx + vy + C2))
+        })
+        .sum();
+    total / f64::from(channels.as_u8())
+}
@@ -1,5 +1,9 @@
+#[cfg(any(feature = "alloc", feature = "std"))]
+use alloc::{borrow::Cow, vec::Vec};
 use core::convert::TryFrom;
 
+use bytemuck::{try_cast_slice, Pod};
+
 use crate::error::{Error, Result};
 use crate::utils::unlikely;
 
@@ -7,10 +11,11 @@ use crate::utils::unlikely;
 ///
 /// Note: the color space is purely informative. Although it is saved to the
 /// file header, it does not affect encoding/decoding in any way.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord, Default)]
 #[repr(u8)]
 pub enum ColorSpace {
     /// sRGB with linear alpha
+    #[default]
     Srgb = 0,
     /// All channels are linear
     Linear = 1,
@@ -33,12 +38,6 @@ impl ColorSpace {
     }
 }
 
-impl Default for ColorSpace {
-    fn default() -> Self {
-        Self::Srgb
-    }
-}
-
 impl From<ColorSpace> for u8 {
     #[inline]
     fn from(colorspace: ColorSpace) -> Self {
@@ -59,11 +58,25 @@ impl TryFrom<u8> for ColorSpace {
     }
 }
 
+/// Byte order of the width and height fields in the image header.
+///
+/// Note: the QOI specification mandates big-endian; little-endian is only provided
+/// for interoperability with non-conforming files.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord, Default)]
+pub enum Endianness {
+    /// Big-endian (as per specification)
+    #[default]
+    Big,
+    /// Little-endian
+    Little,
+}
+
 /// Number of 8-bit channels in a pixel.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord, Default)]
 #[repr(u8)]
 pub enum Channels {
     /// Three 8-bit channels (RGB)
+    #[default]
     Rgb = 3,
     /// Four 8-bit channels (RGBA)
     Rgba = 4,
@@ -84,11 +97,17 @@ impl Channels {
     pub const fn as_u8(self) -> u8 {
         self as u8
     }
-}
 
-impl Default for Channels {
-    fn default() -> Self {
-        Self::Rgb
+    /// Returns the number of channels (3 if RGB, 4 if RGBA).
+    pub const fn count(self) -> u8 {
+        self.as_u8()
+    }
+
+    /// Returns the number of bytes per pixel (3 if RGB, 4 if RGBA).
+    ///
+    /// This is convenient for computing buffer sizes, e.g. `n_pixels * bytes_per_pixel()`.
+    pub const fn bytes_per_pixel(self) -> usize {
+        self as usize
     }
 }
 
@@ -111,3 +130,286 @@ impl TryFrom<u8> for Channels {
         }
     }
 }
+
+/// Source of an output channel when reordering (swizzling) pixel components.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Component {
+    /// Red channel
+    R,
+    /// Green channel
+    G,
+    /// Blue channel
+    B,
+    /// Alpha channel (255 if the source image has no alpha)
+    A,
+    /// Constant 255
+    One,
+    /// Constant 0
+    Zero,
+}
+
+impl Component {
+    /// Picks the value of this component from an RGBA pixel.
+    #[inline]
+    pub const fn pick(self, px: [u8; 4]) -> u8 {
+        match self {
+            Self::R => px[0],
+            Self::G => px[1],
+            Self::B => px[2],
+            Self::A => px[3],
+            Self::One => 0xff,
+            Self::Zero => 0,
+        }
+    }
+}
+
+/// Clockwise rotation of an image by a multiple of 90 degrees.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Rotation {
+    /// 90 degrees clockwise (swaps image width and height)
+    Deg90,
+    /// 180 degrees
+    Deg180,
+    /// 270 degrees clockwise, i.e. 90 degrees counter-clockwise (swaps width and height)
+    Deg270,
+}
+
+impl Rotation {
+    /// Returns true if the rotation swaps image width and height.
+    #[inline]
+    pub const fn swaps_dimensions(self) -> bool {
+        matches!(self, Self::Deg90 | Self::Deg270)
+    }
+}
+
+/// Order of the components within a pixel of a raw (non-QOI) interleaved buffer.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ComponentOrder {
+    /// 3 channels: red, green, blue
+    Rgb,
+    /// 3 channels: blue, green, red
+    Bgr,
+    /// 4 channels: red, green, blue, alpha
+    Rgba,
+    /// 4 channels: blue, green, red, alpha
+    Bgra,
+    /// 4 channels: alpha, red, green, blue
+    Argb,
+    /// 4 channels: alpha, blue, green, red
+    Abgr,
+}
+
+impl ComponentOrder {
+    /// The number of channels this order applies to.
+    #[inline]
+    pub const fn channels(self) -> Channels {
+        match self {
+            Self::Rgb | Self::Bgr => Channels::Rgb,
+            _ => Channels::Rgba,
+        }
+    }
+}
+
+/// Layout of a raw (non-QOI) pixel buffer: the number of channels and how they're stored.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum RawChannels {
+    /// 8-bit components interleaved in the given order (see [`RawChannels::with_order`])
+    Interleaved(ComponentOrder),
+    /// 3 channels packed into a little-endian 16-bit value (5 bits red in the most
+    /// significant bits, 6 bits green, 5 bits blue), 2 bytes per pixel
+    Rgb565,
+}
+
+impl RawChannels {
+    /// Creates an interleaved raw layout with the given number of channels and component order.
+    ///
+    /// Returns [`Error::ComponentOrderMismatch`] if the order doesn't have the same number of
+    /// channels, e.g. [`ComponentOrder::Argb`] requires [`Channels::Rgba`].
+    #[inline]
+    pub const fn with_order(channels: Channels, order: ComponentOrder) -> Result<Self> {
+        if unlikely(channels.as_u8() != order.channels().as_u8()) {
+            return Err(Error::ComponentOrderMismatch { channels, order });
+        }
+        Ok(Self::Interleaved(order))
+    }
+
+    /// The number of channels (after unpacking, for packed layouts).
+    #[inline]
+    pub const fn channels(self) -> Channels {
+        match self {
+            Self::Interleaved(order) => order.channels(),
+            Self::Rgb565 => Channels::Rgb,
+        }
+    }
+
+    /// The order of the components (RGB for packed layouts).
+    #[inline]
+    pub const fn order(self) -> ComponentOrder {
+        match self {
+            Self::Interleaved(order) => order,
+            Self::Rgb565 => ComponentOrder::Rgb,
+        }
+    }
+
+    /// The number of bytes taken by each pixel.
+    #[inline]
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Interleaved(order) => order.channels().bytes_per_pixel(),
+            Self::Rgb565 => 2,
+        }
+    }
+
+    /// The pattern that converts RGB(A) pixels to this component order, as accepted by
+    /// [`swizzle_in_place`] (only the first three entries are used for 3 channels).
+    #[inline]
+    pub const fn pattern(self) -> [Component; 4] {
+        use Component::{A, B, G, R};
+        match self.order() {
+            ComponentOrder::Rgb | ComponentOrder::Rgba => [R, G, B, A],
+            ComponentOrder::Bgr | ComponentOrder::Bgra => [B, G, R, A],
+            ComponentOrder::Argb => [A, R, G, B],
+            ComponentOrder::Abgr => [A, B, G, R],
+        }
+    }
+}
+
+impl TryFrom<(Channels, ComponentOrder)> for RawChannels {
+    type Error = Error;
+
+    #[inline]
+    fn try_from((channels, order): (Channels, ComponentOrder)) -> Result<Self> {
+        Self::with_order(channels, order)
+    }
+}
+
+impl From<Channels> for RawChannels {
+    /// The layout with the components in the standard (RGB or RGBA) order.
+    #[inline]
+    fn from(channels: Channels) -> Self {
+        Self::Interleaved(match channels {
+            Channels::Rgb => ComponentOrder::Rgb,
+            Channels::Rgba => ComponentOrder::Rgba,
+        })
+    }
+}
+
+/// Variant of the encoding algorithm, for compatibility with other encoders.
+///
+/// All of the variants produce valid images of the same size that can be read by any
+/// decoder; they only differ in the choice between equally good operations.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CompatMode {
+    /// Follows the reference encoder exactly (default if the `reference` feature is enabled)
+    Spec,
+    /// Encodes a single repeated pixel as `QOI_OP_INDEX` instead of `QOI_OP_RUN` where
+    /// possible, which is a bit faster to decode (default otherwise)
+    Fast,
+}
+
+impl Default for CompatMode {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl CompatMode {
+    pub(crate) const DEFAULT: Self =
+        if cfg!(feature = "reference") { Self::Spec } else { Self::Fast };
+}
+
+/// Kind of a QOI operation (chunk) in the encoded stream.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum OpKind {
+    /// `QOI_OP_INDEX`: a pixel from the color index
+    Index,
+    /// `QOI_OP_DIFF`: a small difference from the previous pixel
+    Diff,
+    /// `QOI_OP_LUMA`: a difference from the previous pixel, relative to green
+    Luma,
+    /// `QOI_OP_RUN`: a run of repeated previous pixels
+    Run,
+    /// `QOI_OP_RGB`: a full RGB value
+    Rgb,
+    /// `QOI_OP_RGBA`: a full RGBA value
+    Rgba,
+}
+
+/// Byte offsets of the parts of an encoded image.
+///
+/// Can be obtained via [`Encoder::encode_to_vec_with_layout`](crate::Encoder::encode_to_vec_with_layout).
+#[cfg(all(feature = "encode", any(feature = "alloc", feature = "std")))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Layout {
+    /// Offset of the header (always zero)
+    pub header_start: usize,
+    /// Offset of the op stream, right after the header
+    pub body_start: usize,
+    /// Offset of the 8-byte end padding, right after the op stream
+    pub padding_start: usize,
+    /// Total encoded size in bytes, including the trailer metadata (if any)
+    pub total_len: usize,
+}
+
+/// Reinterprets a byte buffer as a slice of 3- or 4-byte pixels without copying.
+///
+/// Pixel arrays `[u8; N]` have an alignment of 1, so any byte buffer (e.g. one received
+/// over FFI) can be safely viewed as pixels as long as its length is a multiple of `N`;
+/// otherwise, [`Error::InvalidPixelDataLength`] is returned. `N` must be 3 or 4.
+#[inline]
+#[allow(clippy::cast_possible_truncation)]
+pub fn try_cast_pixels<const N: usize>(data: &[u8]) -> Result<&[[u8; N]]>
+where
+    [u8; N]: Pod,
+{
+    let channels = Channels::try_from(N.min(0xff) as u8)?;
+    try_cast_slice(data).map_err(|_| Error::InvalidPixelDataLength { size: data.len(), channels })
+}
+
+/// Reorders the components of each pixel of an interleaved buffer in place.
+///
+/// Each channel `i` of a pixel is replaced with the component `pattern[i]` of the original
+/// pixel, like in [`Decoder::decode_swizzled`](crate::Decoder::decode_swizzled); e.g.
+/// `[B, G, R, A]` converts RGBA to BGRA (and back). For 3-channel data, only the first three
+/// entries of the pattern are used and the alpha component reads as 255. Returns
+/// [`Error::InvalidPixelDataLength`] if the length is not a multiple of the number of channels.
+#[inline]
+pub fn swizzle_in_place(
+    data: &mut [u8], channels: Channels, pattern: [Component; 4],
+) -> Result<()> {
+    let n = channels.bytes_per_pixel();
+    if unlikely(data.len() % n != 0) {
+        return Err(Error::InvalidPixelDataLength { size: data.len(), channels });
+    }
+    for px in data.chunks_exact_mut(n) {
+        let src = [px[0], px[1], px[2], px.get(3).copied().unwrap_or(0xff)];
+        for (out, c) in px.iter_mut().zip(pattern) {
+            *out = c.pick(src);
+        }
+    }
+    Ok(())
+}
+
+/// Converts interleaved pixel data between 3 and 4 channels, borrowing it if no conversion
+/// is needed.
+///
+/// Converting RGB to RGBA sets alpha to 255, and converting RGBA to RGB drops the alpha
+/// channel. If `from == to`, the input is returned as is (`Cow::Borrowed`) after checking
+/// that its length is a multiple of the number of channels.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[inline]
+pub fn maybe_convert_channels(data: &[u8], from: Channels, to: Channels) -> Result<Cow<'_, [u8]>> {
+    let (n_from, n_to) = (from.bytes_per_pixel(), to.bytes_per_pixel());
+    if unlikely(data.len() % n_from != 0) {
+        return Err(Error::InvalidPixelDataLength { size: data.len(), channels: from });
+    }
+    if from == to {
+        return Ok(Cow::Borrowed(data));
+    }
+    let mut out = Vec::with_capacity(data.len() / n_from * n_to);
+    for px in data.chunks_exact(n_from) {
+        out.extend_from_slice(&[px[0], px[1], px[2], px.get(3).copied().unwrap_or(0xff)][..n_to]);
+    }
+    Ok(Cow::Owned(out))
+}
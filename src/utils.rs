@@ -1,7 +1,10 @@
-#[cfg(feature = "std")]
+#[cfg(all(feature = "encode", any(feature = "std", feature = "alloc")))]
+use alloc::vec::Vec;
+#[cfg(all(feature = "encode", feature = "std"))]
 use std::io::Write;
 
-use crate::error::Result;
+#[cfg(feature = "encode")]
+use crate::error::{Error, Result};
 
 #[inline(always)]
 #[cold]
@@ -24,14 +27,17 @@ pub const fn unlikely(b: bool) -> bool {
     b
 }
 
+#[cfg(feature = "encode")]
 pub trait Writer: Sized {
     fn write_one(self, v: u8) -> Result<Self>;
     fn write_many(self, v: &[u8]) -> Result<Self>;
     fn capacity(&self) -> usize;
 }
 
+#[cfg(feature = "encode")]
 pub struct BytesMut<'a>(&'a mut [u8]);
 
+#[cfg(feature = "encode")]
 impl<'a> BytesMut<'a> {
     pub fn new(buf: &'a mut [u8]) -> Self {
         Self(buf)
@@ -59,7 +65,8 @@ impl<'a> BytesMut<'a> {
     }
 }
 
-impl<'a> Writer for BytesMut<'a> {
+#[cfg(feature = "encode")]
+impl Writer for BytesMut<'_> {
     #[inline]
     fn write_one(self, v: u8) -> Result<Self> {
         Ok(BytesMut::write_one(self, v))
@@ -76,20 +83,159 @@ impl<'a> Writer for BytesMut<'a> {
     }
 }
 
-#[cfg(feature = "std")]
+/// Writer that doesn't store anything and only counts the bytes written.
+#[cfg(feature = "encode")]
+pub struct CountingWriter(usize);
+
+#[cfg(feature = "encode")]
+impl CountingWriter {
+    pub const fn new() -> Self {
+        Self(0)
+    }
+}
+
+#[cfg(feature = "encode")]
+impl Writer for CountingWriter {
+    #[inline]
+    fn write_one(self, _: u8) -> Result<Self> {
+        Ok(Self(self.0 + 1))
+    }
+
+    #[inline]
+    fn write_many(self, v: &[u8]) -> Result<Self> {
+        Ok(Self(self.0 + v.len()))
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        usize::MAX - self.0
+    }
+}
+
+/// Writer into a small fixed-size array that can be drained byte by byte.
+#[cfg(feature = "encode")]
+pub struct ArrayBuf<const N: usize> {
+    buf: [u8; N],
+    start: usize,
+    end: usize,
+}
+
+#[cfg(feature = "encode")]
+impl<const N: usize> ArrayBuf<N> {
+    pub const fn new() -> Self {
+        Self { buf: [0; N], start: 0, end: 0 }
+    }
+
+    #[inline]
+    pub fn pop_front(&mut self) -> Option<u8> {
+        let v = *self.buf[..self.end].get(self.start)?;
+        self.start += 1;
+        Some(v)
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        (self.start, self.end) = (0, 0);
+    }
+}
+
+#[cfg(feature = "encode")]
+impl<const N: usize> Writer for &mut ArrayBuf<N> {
+    #[inline]
+    fn write_one(self, v: u8) -> Result<Self> {
+        self.write_many(&[v])
+    }
+
+    #[inline]
+    fn write_many(self, v: &[u8]) -> Result<Self> {
+        let end = self.end + v.len();
+        let tail = self
+            .buf
+            .get_mut(self.end..end)
+            .ok_or(Error::OutputBufferTooSmall { size: N, required: end })?;
+        tail.copy_from_slice(v);
+        self.end = end;
+        Ok(self)
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        N - self.end
+    }
+}
+
+#[cfg(all(feature = "encode", any(feature = "std", feature = "alloc")))]
+impl Writer for &mut Vec<u8> {
+    #[inline]
+    fn write_one(self, v: u8) -> Result<Self> {
+        self.push(v);
+        Ok(self)
+    }
+
+    #[inline]
+    fn write_many(self, v: &[u8]) -> Result<Self> {
+        self.extend_from_slice(v);
+        Ok(self)
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        usize::MAX - self.len()
+    }
+}
+
+/// Writer that fails once more than `limit` bytes in total would have been written, so that
+/// encoding is aborted early; the first `n_written` bytes are written elsewhere beforehand.
+#[cfg(all(feature = "encode", feature = "std"))]
+pub struct LimitWriter<W> {
+    writer: W,
+    n_written: usize,
+    limit: usize,
+}
+
+#[cfg(all(feature = "encode", feature = "std"))]
+impl<W: Writer> LimitWriter<W> {
+    pub const fn new(writer: W, n_written: usize, limit: usize) -> Self {
+        Self { writer, n_written, limit }
+    }
+}
+
+#[cfg(all(feature = "encode", feature = "std"))]
+impl<W: Writer> Writer for LimitWriter<W> {
+    #[inline]
+    fn write_one(self, v: u8) -> Result<Self> {
+        self.write_many(&[v])
+    }
+
+    #[inline]
+    fn write_many(self, v: &[u8]) -> Result<Self> {
+        let n_written = self.n_written + v.len();
+        if unlikely(n_written > self.limit) {
+            return Err(Error::SizeBudgetExceeded { size: n_written, budget: self.limit });
+        }
+        Ok(Self { writer: self.writer.write_many(v)?, n_written, ..self })
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.writer.capacity().min(self.limit.saturating_sub(self.n_written))
+    }
+}
+
+#[cfg(all(feature = "encode", feature = "std"))]
 pub struct GenericWriter<W> {
     writer: W,
     n_written: usize,
 }
 
-#[cfg(feature = "std")]
+#[cfg(all(feature = "encode", feature = "std"))]
 impl<W: Write> GenericWriter<W> {
     pub const fn new(writer: W) -> Self {
         Self { writer, n_written: 0 }
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(all(feature = "encode", feature = "std"))]
 impl<W: Write> Writer for GenericWriter<W> {
     fn write_one(mut self, v: u8) -> Result<Self> {
         self.n_written += 1;
@@ -10,3 +10,37 @@ pub fn hash<const N: usize>(px: [u8; N]) -> u8 {
     let am = a.wrapping_mul(11);
     rm.wrapping_add(gm).wrapping_add(bm).wrapping_add(am) % 64
 }
+
+/// Global allocator wrapper counting the allocations made by the current thread.
+///
+/// Counting per thread keeps the tests running in parallel from affecting each other.
+#[allow(unused)]
+pub struct CountingAllocator;
+
+std::thread_local! {
+    static N_ALLOCS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[allow(unused)]
+impl CountingAllocator {
+    /// Returns the number of allocations made by the current thread so far.
+    pub fn count() -> usize {
+        N_ALLOCS.with(std::cell::Cell::get)
+    }
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        N_ALLOCS.with(|n| n.set(n.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, size: usize) -> *mut u8 {
+        N_ALLOCS.with(|n| n.set(n.get() + 1));
+        std::alloc::System.realloc(ptr, layout, size)
+    }
+}
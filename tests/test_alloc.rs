@@ -0,0 +1,52 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
+mod common;
+
+use qoi::{encode_to_vec, Decoder, Encoder, Result};
+
+use self::common::CountingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn gen_image(width: u32, height: u32, channels: usize) -> Vec<u8> {
+    (0..width as usize * height as usize * channels).map(|i| ((i * 7) ^ (i / 13)) as u8).collect()
+}
+
+fn count_allocs<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = CountingAllocator::count();
+    let res = f();
+    (res, CountingAllocator::count() - before)
+}
+
+#[test]
+fn test_encode_into_vec_no_alloc() -> Result<()> {
+    let (width, height) = (37, 23);
+    for channels in [3, 4] {
+        let data = gen_image(width, height, channels);
+        let encoder = Encoder::new(&data, width, height)?;
+        let mut out = Vec::new();
+        let (size, n_allocs) = count_allocs(|| encoder.encode_into_vec(&mut out));
+        assert_ne!(n_allocs, 0);
+        assert_eq!(size?, out.len());
+        assert_eq!(out, encode_to_vec(&data, width, height)?);
+        let (size, n_allocs) = count_allocs(|| encoder.encode_into_vec(&mut out));
+        assert_eq!(n_allocs, 0);
+        assert_eq!(size?, out.len());
+        assert_eq!(out, encode_to_vec(&data, width, height)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_to_buf_no_alloc() -> Result<()> {
+    let (width, height) = (37, 23);
+    let data = gen_image(width, height, 4);
+    let encoded = encode_to_vec(&data, width, height)?;
+    let mut buf = vec![0; data.len()];
+    let (res, n_allocs) = count_allocs(|| Decoder::new(&encoded)?.decode_to_buf(&mut buf));
+    assert_eq!(n_allocs, 0);
+    assert_eq!(res?, data.len());
+    assert_eq!(buf, data);
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
 mod common;
 
 use bytemuck::{cast_slice, Pod};
@@ -0,0 +1,181 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
+use qoi::{
+    concat_horizontal, decode_banded, decode_progressive_level, decode_sequence_dedup,
+    decode_to_vec, encode_progressive, encode_sequence_dedup, encode_to_vec, reencode_banded,
+    rotate, stack_vertical, transcode_add_alpha, Channels, ColorSpace, Encoder, Error, Result,
+    Rotation,
+};
+
+#[test]
+fn test_stack_vertical() -> Result<()> {
+    let top = [[1, 2, 3], [4, 5, 6], [7, 8, 9], [10, 11, 12]].concat();
+    let bottom = [[13, 14, 15], [16, 17, 18], [19, 20, 21], [22, 23, 24]].concat();
+    let stacked = stack_vertical(&encode_to_vec(&top, 2, 2)?, &encode_to_vec(&bottom, 2, 2)?)?;
+    let (header, decoded) = decode_to_vec(stacked)?;
+    assert_eq!((header.width, header.height, header.channels), (2, 4, Channels::Rgb));
+    assert_eq!(decoded, (1..=24).collect::<Vec<u8>>());
+
+    let res = stack_vertical(&encode_to_vec(&top, 2, 2)?, &encode_to_vec(&bottom, 4, 1)?);
+    assert!(matches!(res, Err(Error::IncompatibleImages { .. })));
+    let res = stack_vertical(&encode_to_vec(&top, 2, 2)?, &encode_to_vec(&top[..8], 2, 1)?);
+    assert!(matches!(res, Err(Error::IncompatibleImages { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_concat_horizontal() -> Result<()> {
+    let left = [[1, 2, 3, 4], [5, 6, 7, 8], [17, 18, 19, 20], [21, 22, 23, 24]].concat();
+    let right = [[9, 10, 11, 12], [13, 14, 15, 16], [25, 26, 27, 28], [29, 30, 31, 32]].concat();
+    let concat = concat_horizontal(&encode_to_vec(&left, 2, 2)?, &encode_to_vec(&right, 2, 2)?)?;
+    let (header, decoded) = decode_to_vec(concat)?;
+    assert_eq!((header.width, header.height, header.channels), (4, 2, Channels::Rgba));
+    assert_eq!(decoded, (1..=32).collect::<Vec<u8>>());
+
+    let res = concat_horizontal(&encode_to_vec(&left, 2, 2)?, &encode_to_vec(&right, 4, 1)?);
+    assert!(matches!(res, Err(Error::IncompatibleImages { .. })));
+    let res = concat_horizontal(&encode_to_vec(&left, 2, 2)?, &encode_to_vec(&right[..12], 2, 2)?);
+    assert!(matches!(res, Err(Error::IncompatibleImages { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_transcode_add_alpha() -> Result<()> {
+    let (width, height) = (23, 5);
+    let rgb = (0..width * height * 3).map(|i| (i / 7 * 5) as u8).collect::<Vec<_>>();
+    let encoded = encode_to_vec(&rgb, width, height)?;
+    let transcoded = transcode_add_alpha(&encoded, 200)?;
+    let (header, rgba) = decode_to_vec(&transcoded)?;
+    assert_eq!((header.width, header.height, header.channels), (width, height, Channels::Rgba));
+    let expected =
+        rgb.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 200]).collect::<Vec<_>>();
+    assert_eq!(rgba, expected);
+    assert_eq!(transcoded, encode_to_vec(&expected, width, height)?);
+
+    // the alpha of 4-channel images is replaced too
+    assert_eq!(decode_to_vec(transcode_add_alpha(&transcoded, 7)?)?.1[3], 7);
+    assert!(transcode_add_alpha(&encoded[..20], 200).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_encode_progressive() -> Result<()> {
+    #[rustfmt::skip]
+    let data = [
+        0, 4, 8, 12,
+        4, 8, 12, 16,
+        100, 100, 50, 50,
+        100, 101, 50, 51,
+    ];
+    let data: Vec<u8> = data.iter().flat_map(|&v| [v, 255 - v, 7]).collect();
+    let encoded = encode_progressive(&data, 4, 4, Channels::Rgb, 5)?;
+
+    let (header, level) = decode_progressive_level(&encoded, 0)?;
+    assert_eq!((header.width, header.height), (1, 1));
+    assert_eq!(level, [42, 214, 7]);
+    let (header, level) = decode_progressive_level(&encoded, 1)?;
+    assert_eq!((header.width, header.height), (2, 2));
+    assert_eq!(level, [4, 251, 7, 12, 243, 7, 100, 155, 7, 50, 205, 7]);
+    let (header, level) = decode_progressive_level(&encoded, 2)?;
+    assert_eq!((header.width, header.height, header.channels), (4, 4, Channels::Rgb));
+    assert_eq!(level, data);
+    assert!(decode_progressive_level(&encoded, 3).is_err());
+
+    // odd dimensions are rounded up, and a single level is just the full image
+    let encoded = encode_progressive(&data[..3 * 3 * 3], 3, 3, Channels::Rgb, 2)?;
+    let (header, level) = decode_progressive_level(&encoded, 0)?;
+    assert_eq!((header.width, header.height), (2, 2));
+    assert_eq!(&level[9..], [100, 155, 7]);
+    let encoded = encode_progressive(&data, 4, 4, Channels::Rgb, 1)?;
+    assert_eq!(encoded, encode_to_vec(&data, 4, 4)?);
+    assert!(encode_progressive(&data, 4, 4, Channels::Rgba, 1).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_encode_sequence_dedup() -> Result<()> {
+    let a = (0..8 * 8 * 4).map(|i| (i * 13 % 251) as u8).collect::<Vec<_>>();
+    let b = a.iter().map(|v| v ^ 1).collect::<Vec<_>>();
+    let frames = [&a, &a, &b, &a];
+    let encoded = encode_sequence_dedup(&frames, 8, 8)?;
+    let (enc_a, enc_b) = (encode_to_vec(&a, 8, 8)?, encode_to_vec(&b, 8, 8)?);
+    // the second frame is a single marker byte, the last one isn't a repeat of its predecessor
+    assert_eq!(encoded, [&enc_a[..], &[0], &enc_b, &enc_a].concat());
+    assert!(encoded.len() < 3 * enc_a.len() + enc_b.len());
+
+    let decoded = decode_sequence_dedup(&encoded)?;
+    assert_eq!(decoded.len(), 4);
+    for ((header, pixels), frame) in decoded.iter().zip(frames) {
+        assert_eq!((header.width, header.height), (8, 8));
+        assert_eq!(pixels, frame);
+    }
+    assert!(decode_sequence_dedup(&[]).unwrap().is_empty());
+    assert!(matches!(decode_sequence_dedup(&encoded[1..]), Err(Error::InvalidMagic { .. })));
+    assert!(decode_sequence_dedup(&[0]).is_err());
+    assert!(decode_sequence_dedup(&encoded[..encoded.len() - 1]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_reencode_banded() -> Result<()> {
+    let (width, height) = (9, 10);
+    let data = (0..width * height * 3).map(|i| (i * 7 % 253) as u8).collect::<Vec<_>>();
+    let encoded = Encoder::new(&data, width, height)?.with_colorspace(ColorSpace::Linear);
+    let encoded = encoded.encode_to_vec()?;
+    let banded = reencode_banded(&encoded, 4)?;
+
+    // 3 bands of 4, 4 and 2 rows, each a standalone image
+    assert_eq!(banded[..4], 3_u32.to_be_bytes());
+    let lens = banded[4..16].chunks(4).map(|b| u32::from_be_bytes(b.try_into().unwrap()));
+    let mut offset = 16;
+    for (len, rows) in lens.zip([0..4, 4..8, 8..10]) {
+        let band = &banded[offset..offset + len as usize];
+        let (header, pixels) = decode_to_vec(band)?;
+        assert_eq!((header.height, header.colorspace), (rows.len() as u32, ColorSpace::Linear));
+        assert_eq!(pixels, data[rows.start * 27..rows.end * 27]);
+        offset += len as usize;
+    }
+    assert_eq!(offset, banded.len());
+
+    let (header, decoded) = decode_banded(&banded)?;
+    assert_eq!(header, decode_to_vec(&encoded)?.0);
+    assert_eq!(decoded, data);
+    assert_eq!(decode_banded(&reencode_banded(&encoded, 0)?)?.1, data);
+    assert_eq!(decode_banded(&reencode_banded(&encoded, 100)?)?.1, data);
+    assert!(decode_banded(&banded[..banded.len() - 1]).is_err());
+    assert!(decode_banded(&[0, 0, 0, 0]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_rotate() -> Result<()> {
+    let (w, h) = (5_usize, 3_usize);
+    for bpp in [3, 4] {
+        let px =
+            |x: usize, y: usize| (0..bpp).map(|c| (y * 50 + x * 8 + c) as u8).collect::<Vec<_>>();
+        let data = (0..h).flat_map(|y| (0..w).flat_map(move |x| px(x, y))).collect::<Vec<_>>();
+        let encoded = Encoder::new(&data, w as u32, h as u32)?.with_colorspace(ColorSpace::Linear);
+        let encoded = encoded.encode_to_vec()?;
+        let (tl, tr, bl, br) = (px(0, 0), px(w - 1, 0), px(0, h - 1), px(w - 1, h - 1));
+
+        for (rotation, (out_w, out_h), corners) in [
+            (Rotation::Deg90, (h, w), [&bl, &tl, &br, &tr]),
+            (Rotation::Deg180, (w, h), [&br, &bl, &tr, &tl]),
+            (Rotation::Deg270, (h, w), [&tr, &br, &tl, &bl]),
+        ] {
+            let (header, rotated) = decode_to_vec(rotate(&encoded, rotation)?)?;
+            assert_eq!((header.width, header.height), (out_w as u32, out_h as u32));
+            assert_eq!(header.channels.as_u8() as usize, bpp);
+            assert_eq!(header.colorspace, ColorSpace::Linear);
+            let at = |x: usize, y: usize| &rotated[(y * out_w + x) * bpp..][..bpp];
+            let actual = [at(0, 0), at(out_w - 1, 0), at(0, out_h - 1), at(out_w - 1, out_h - 1)];
+            assert_eq!(actual, corners.map(Vec::as_slice), "{rotation:?}");
+        }
+
+        let twice = rotate(&rotate(&encoded, Rotation::Deg90)?, Rotation::Deg90)?;
+        assert_eq!(twice, rotate(&encoded, Rotation::Deg180)?);
+        let back = rotate(&rotate(&encoded, Rotation::Deg90)?, Rotation::Deg270)?;
+        assert_eq!(decode_to_vec(back)?.1, data);
+    }
+    Ok(())
+}
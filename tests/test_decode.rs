@@ -0,0 +1,727 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
+use std::io::ErrorKind;
+
+use qoi::{
+    count_ops, decode_scanline, decode_to_vec, decode_with_context, encode_to_vec, op_length,
+    repair_dimensions, Channels, ColorSpace, Component, DecodeContext, DecodeResult, Decoder,
+    Encoder, Error, ProgressiveDecoder, Result,
+};
+
+fn gen_image(width: u32, height: u32, channels: usize) -> Vec<u8> {
+    (0..width as usize * height as usize * channels).map(|i| ((i * 7) ^ (i / 13)) as u8).collect()
+}
+
+#[test]
+fn test_decode_for_each_row() -> Result<()> {
+    for (channels, out_channels) in [(3, Channels::Rgb), (4, Channels::Rgba), (4, Channels::Rgb)] {
+        let (width, height) = (13, 7);
+        let data = gen_image(width, height, channels);
+        let encoded = encode_to_vec(&data, width, height)?;
+        let expected = Decoder::new(&encoded)?.with_channels(out_channels).decode_to_vec()?;
+        let mut decoder = Decoder::new(&encoded)?.with_channels(out_channels);
+        let mut rows = Vec::new();
+        decoder.decode_for_each_row(|y, row| {
+            assert_eq!(y, rows.len());
+            rows.push(row.to_vec());
+            Ok(())
+        })?;
+        assert_eq!(rows.len(), height as usize);
+        assert_eq!(rows.concat(), expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_for_each_row_abort() -> Result<()> {
+    let data = vec![0xaa; 4 * 4 * 3];
+    let encoded = encode_to_vec(&data, 4, 4)?;
+    let mut n_rows = 0;
+    let res = Decoder::new(&encoded)?.decode_for_each_row(|y, _| {
+        n_rows += 1;
+        if y == 1 {
+            Err(qoi::Error::UnexpectedBufferEnd)
+        } else {
+            Ok(())
+        }
+    });
+    assert!(matches!(res, Err(qoi::Error::UnexpectedBufferEnd)));
+    assert_eq!(n_rows, 2);
+    assert_eq!(decode_to_vec(&encoded)?.1, data);
+    Ok(())
+}
+
+#[test]
+fn test_decode_swizzled() -> Result<()> {
+    use Component::{One, Zero, A, B, G, R};
+
+    let pixels = [[1, 2, 3, 4], [10, 20, 30, 40], [10, 20, 30, 40], [5, 6, 7, 8]];
+    let encoded = encode_to_vec(pixels.concat(), 2, 2)?;
+    let decode = |pattern| -> Result<Vec<u8>> {
+        let mut out = vec![0; 16];
+        assert_eq!(Decoder::new(&encoded)?.decode_swizzled(pattern, &mut out)?, 16);
+        Ok(out)
+    };
+    let expected = |f: fn([u8; 4]) -> [u8; 4]| pixels.map(f).concat();
+
+    assert_eq!(decode([B, G, R, A])?, expected(|[r, g, b, a]| [b, g, r, a]));
+    assert_eq!(decode([A, A, A, A])?, expected(|[.., a]| [a, a, a, a]));
+    assert_eq!(decode([R, G, B, One])?, expected(|[r, g, b, _]| [r, g, b, 0xff]));
+    assert_eq!(decode([Zero, G, Zero, One])?, expected(|[_, g, ..]| [0, g, 0, 0xff]));
+
+    let rgb = encode_to_vec([1, 2, 3, 4, 5, 6], 2, 1)?;
+    let mut out = [0; 8];
+    Decoder::new(&rgb)?.decode_swizzled([A, B, G, R], &mut out)?;
+    assert_eq!(out, [0xff, 3, 2, 1, 0xff, 6, 5, 4]);
+    Ok(())
+}
+
+#[test]
+fn test_decode_subsampled() -> Result<()> {
+    let data = (0..16_u8).flat_map(|i| [i, i * 2, i * 3]).collect::<Vec<_>>();
+    let encoded = encode_to_vec(&data, 4, 4)?;
+    let (w, h, out) = Decoder::new(&encoded)?.decode_subsampled(2)?;
+    assert_eq!((w, h), (2, 2));
+    let expected = [0_u8, 2, 8, 10].iter().flat_map(|&i| [i, i * 2, i * 3]).collect::<Vec<_>>();
+    assert_eq!(out, expected);
+
+    let (w, h, out) = Decoder::new(&encoded)?.decode_subsampled(3)?;
+    assert_eq!((w, h), (2, 2));
+    let expected = [0_u8, 3, 12, 15].iter().flat_map(|&i| [i, i * 2, i * 3]).collect::<Vec<_>>();
+    assert_eq!(out, expected);
+
+    assert_eq!(Decoder::new(&encoded)?.decode_subsampled(1)?.2, data);
+    Ok(())
+}
+
+#[test]
+fn test_decode_zero_copy() -> Result<()> {
+    let (width, height) = (70, 5);
+    let solid = [10, 20, 30, 40].repeat(width as usize * height as usize);
+    let encoded = encode_to_vec(&solid, width, height)?;
+    let result = Decoder::new(&encoded)?.try_decode_zero_copy()?;
+    assert_eq!(result, DecodeResult::Solid([10, 20, 30, 40], 350));
+    let result = Decoder::new(&encoded)?.with_channels(Channels::Rgb).try_decode_zero_copy()?;
+    assert_eq!(result, DecodeResult::Solid([10, 20, 30, 255], 350));
+
+    let mut data = [1, 2, 3].repeat(width as usize * height as usize);
+    data[3 * 150] = 4; // a pixel in the third row
+    for data in [data, gen_image(width, height, 3)] {
+        let encoded = encode_to_vec(&data, width, height)?;
+        let result = Decoder::new(&encoded)?.try_decode_zero_copy()?;
+        assert_eq!(result, DecodeResult::Full(data));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_remaining() -> Result<()> {
+    let (width, height) = (13, 8);
+    let encoded = encode_to_vec(gen_image(width, height, 4), width, height)?;
+    let mut decoder = Decoder::new(&encoded)?;
+    assert_eq!(decoder.pixels_remaining(), 13 * 8);
+    assert_eq!(decoder.bytes_remaining(), encoded.len() - 14);
+    let abort_at_half = |y, _: &[u8]| if y == 3 { Err(Error::InvalidPadding) } else { Ok(()) };
+    assert!(decoder.decode_for_each_row(abort_at_half).is_err());
+    assert_eq!(decoder.pixels_remaining(), 13 * 4);
+    assert!(decoder.bytes_remaining() > 8 && decoder.bytes_remaining() < encoded.len() - 14);
+    assert_eq!(decoder.bytes_remaining(), decoder.data().len());
+    let mut decoder = Decoder::new(&encoded)?;
+    decoder.decode_to_vec()?;
+    assert_eq!((decoder.pixels_remaining(), decoder.bytes_remaining()), (0, 0));
+    Ok(())
+}
+
+#[test]
+fn test_decode_missing_padding() -> Result<()> {
+    let (width, height) = (13, 7);
+    let data = gen_image(width, height, 4);
+    let encoded = encode_to_vec(&data, width, height)?;
+    for n_padding in 0..8 {
+        let truncated = &encoded[..encoded.len() - 8 + n_padding];
+        assert!(Decoder::new(truncated)?.decode_to_vec().is_err());
+        assert!(Decoder::from_stream(truncated)?.decode_to_vec().is_err());
+        let mut decoder = Decoder::new(truncated)?.with_allow_missing_padding(true);
+        assert_eq!(decoder.decode_to_vec()?, data);
+        assert!(decoder.data().is_empty());
+        let mut decoder = Decoder::from_stream(truncated)?.with_allow_missing_padding(true);
+        assert_eq!(decoder.decode_to_vec()?, data);
+    }
+    let mut corrupt = encoded.clone();
+    corrupt[encoded.len() - 1] = 2;
+    assert!(Decoder::new(&corrupt)?.with_allow_missing_padding(true).decode_to_vec().is_err());
+    let mut decoder = Decoder::from_stream(&corrupt[..])?.with_allow_missing_padding(true);
+    assert!(decoder.decode_to_vec().is_err());
+    let truncated = &encoded[..encoded.len() - 9];
+    assert!(Decoder::new(truncated)?.with_allow_missing_padding(true).decode_to_vec().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_decode_partial() -> Result<()> {
+    let (width, height) = (13, 7);
+    let data = gen_image(width, height, 3);
+    let encoded = encode_to_vec(&data, width, height)?;
+    let mut decoder = Decoder::new(&encoded)?;
+    let (head, has_more) = decoder.decode_partial(4)?;
+    assert_eq!((&head[..], has_more), (&data[..12], true));
+
+    let mut decoder = Decoder::new(&encoded)?.with_channels(Channels::Rgba);
+    let mut decoded = Vec::<u8>::new();
+    loop {
+        let (chunk, has_more) = decoder.decode_partial(10)?;
+        decoded.extend(chunk.chunks_exact(4).flat_map(|px| &px[..3]));
+        if !has_more {
+            break;
+        }
+    }
+    assert_eq!(decoded, data);
+    assert_eq!(decoder.decode_partial(10)?, (vec![], false));
+    Ok(())
+}
+
+#[test]
+fn test_decode_run_overflow() -> Result<()> {
+    let mut encoded = b"qoif\x00\x00\x00\x03\x00\x00\x00\x01\x03\x00".to_vec();
+    encoded.extend([0xfe, 100, 150, 200, 0xc0 | 9]); // the run covers 10 pixels out of 2 left
+    encoded.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+    let res = decode_to_vec(&encoded);
+    assert!(matches!(res, Err(Error::RunOverflow)));
+    let res = Decoder::from_stream(&encoded[..])?.decode_to_vec();
+    assert!(matches!(res, Err(Error::RunOverflow)));
+    assert!(matches!(qoi::average_color(&encoded), Err(Error::RunOverflow)));
+
+    encoded[18] = 0xc0 | 1; // exactly 2 pixels
+    assert_eq!(decode_to_vec(&encoded)?.1, [100, 150, 200].repeat(3));
+    Ok(())
+}
+
+#[test]
+fn test_decode_transposed() -> Result<()> {
+    // 2x3 image with pixels numbered row by row
+    let data = (1..=6).flat_map(|i| [i, i * 10, i * 20]).collect::<Vec<u8>>();
+    let encoded = encode_to_vec(&data, 2, 3)?;
+    let (header, transposed) = Decoder::new(&encoded)?.decode_transposed()?;
+    assert_eq!((header.width, header.height, header.channels), (3, 2, Channels::Rgb));
+    let expected =
+        [1, 3, 5, 2, 4, 6].iter().flat_map(|&i| [i, i * 10, i * 20]).collect::<Vec<u8>>();
+    assert_eq!(transposed, expected);
+
+    let mut decoder = Decoder::new(&encoded)?.with_channels(Channels::Rgba);
+    let (header, transposed) = decoder.decode_transposed()?;
+    assert_eq!(header.channels, Channels::Rgba);
+    assert_eq!(transposed[4..8], [3, 30, 60, 255]);
+    Ok(())
+}
+
+#[test]
+fn test_decode_from_parts() -> Result<()> {
+    let (width, height) = (11, 6);
+    let encoded = encode_to_vec(gen_image(width, height, 4), width, height)?;
+    let (header, expected) = decode_to_vec(&encoded)?;
+    let body = &encoded[14..];
+    assert_eq!(Decoder::from_parts(&header, body)?.decode_to_vec()?, expected);
+
+    let unpadded = &body[..body.len() - 8];
+    assert!(Decoder::from_parts(&header, unpadded)?.decode_to_vec().is_err());
+    let mut decoder = Decoder::from_parts(&header, unpadded)?.with_allow_missing_padding(true);
+    assert_eq!(decoder.decode_to_vec()?, expected);
+
+    let header = qoi::Header { width: 0, ..header };
+    assert!(matches!(
+        Decoder::from_parts(&header, body),
+        Err(Error::InvalidImageDimensions { width: 0, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_decode_to_f32_vec() -> Result<()> {
+    let data = [0, 128, 255, 255, 10, 188, 0, 128];
+    let encoded = encode_to_vec(data, 2, 1)?;
+    let (header, values) = Decoder::new(&encoded)?.decode_to_f32_vec(false)?;
+    assert_eq!(header.channels, Channels::Rgba);
+    let expected = data.iter().map(|&c| f32::from(c) / 255.).collect::<Vec<_>>();
+    assert_eq!(values, expected);
+    assert_eq!((values[0], values[2]), (0.0, 1.0));
+
+    // sRGB 50% gray is ~21.4% in linear light; alpha is left untouched
+    let (header, linear) =
+        Decoder::new(&encoded)?.with_channels(Channels::Rgb).decode_to_f32_vec(true)?;
+    assert_eq!(header.channels, Channels::Rgb);
+    assert_eq!(linear.len(), 6);
+    assert_eq!((linear[0], linear[2]), (0.0, 1.0));
+    assert!((linear[1] - 0.2159).abs() < 1e-3);
+    assert!((linear[3] - 0.0030).abs() < 1e-4);
+    let (_, linear) = Decoder::new(&encoded)?.decode_to_f32_vec(true)?;
+    assert_eq!(linear[7], 128. / 255.);
+
+    // linear-tagged images are never converted
+    let encoded =
+        qoi::Encoder::new(&data, 2, 1)?.with_colorspace(qoi::ColorSpace::Linear).encode_to_vec()?;
+    assert_eq!(Decoder::new(&encoded)?.decode_to_f32_vec(true)?.1, expected);
+    Ok(())
+}
+
+#[test]
+fn test_decode_pixel_count_mismatch() -> Result<()> {
+    let header = b"qoif\x00\x00\x00\x03\x00\x00\x00\x01\x03\x00";
+    let with_ops = |ops: &[u8]| [&header[..], ops, &[0, 0, 0, 0, 0, 0, 0, 1]].concat();
+    let expected = [100, 150, 200].repeat(3);
+    assert_eq!(decode_to_vec(with_ops(&[0xfe, 100, 150, 200, 0xc1]))?.1, expected);
+
+    // one pixel short: the decoder runs into the padding
+    let short = with_ops(&[0xfe, 100, 150, 200, 0xc0]);
+    let res = decode_to_vec(&short);
+    assert!(matches!(res, Err(Error::PixelCountMismatch { got: 2, expected: 3 })));
+    let res = Decoder::from_stream(&short[..])?.decode_to_vec();
+    assert!(matches!(res, Err(Error::PixelCountMismatch { got: 2, expected: 3 })));
+    // if disabled, the underlying error is reported instead
+    let res = Decoder::new(&short)?.with_pixel_count_check(false).decode_to_vec();
+    assert!(matches!(res, Err(Error::UnexpectedBufferEnd)));
+    let res = Decoder::from_stream(&short[..])?.with_pixel_count_check(false).decode_to_vec();
+    assert!(matches!(res, Err(Error::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof));
+
+    // one pixel too many: an extra op precedes the padding
+    let long = with_ops(&[0xfe, 100, 150, 200, 0xc1, 0x6a]);
+    let res = decode_to_vec(&long);
+    assert!(matches!(res, Err(Error::PixelCountMismatch { got: 4, expected: 3 })));
+    let res = Decoder::from_stream(&long[..])?.decode_to_vec();
+    assert!(matches!(res, Err(Error::PixelCountMismatch { got: 4, expected: 3 })));
+    let mut decoder = Decoder::new(&long)?.with_pixel_count_check(false);
+    assert_eq!(decoder.decode_to_vec()?, expected);
+    assert_eq!(decoder.bytes_remaining(), 0);
+    let mut decoder = Decoder::from_stream(&long[..])?.with_pixel_count_check(false);
+    assert_eq!(decoder.decode_to_vec()?, expected);
+    assert!(decoder.reader().is_empty());
+
+    // garbage instead of the padding is neither
+    let garbage = [&long[..long.len() - 8], &[0xfe, 1, 2, 3, 0, 0, 0, 0, 0]].concat();
+    assert!(matches!(decode_to_vec(&garbage), Err(Error::InvalidPadding)));
+    let res = Decoder::from_stream(&garbage[..])?.decode_to_vec();
+    assert!(matches!(res, Err(Error::InvalidPadding)));
+    Ok(())
+}
+
+#[test]
+fn test_decode_rgba_as_rgb_index() -> Result<()> {
+    // the index must be keyed by the source alpha even when it's not part of the output
+    let data = [
+        [10, 20, 30, 40],
+        [50, 60, 70, 80],
+        [200, 100, 0, 80],
+        [10, 20, 30, 40],
+        [50, 60, 70, 80],
+        [200, 100, 0, 80],
+    ]
+    .concat();
+    let encoded = encode_to_vec(&data, 3, 2)?;
+    let expected: Vec<u8> = data.chunks_exact(4).flat_map(|px| px[..3].to_vec()).collect();
+    let decoded = Decoder::new(&encoded)?.with_channels(Channels::Rgb).decode_to_vec()?;
+    assert_eq!(decoded, expected);
+    let decoded =
+        Decoder::from_stream(&encoded[..])?.with_channels(Channels::Rgb).decode_to_vec()?;
+    assert_eq!(decoded, expected);
+    Ok(())
+}
+
+#[test]
+fn test_decode_strict() -> Result<()> {
+    let data = gen_image(5, 3, 4);
+    let encoded = encode_to_vec(&data, 5, 3)?;
+    assert_eq!(Decoder::new(&encoded)?.strict().decode_to_vec()?, data);
+    assert_eq!(Decoder::from_stream(&encoded[..])?.strict().decode_to_vec()?, data);
+
+    // an extra op after the end marker is ignored by default
+    let extra = [&encoded[..], &[0xc0]].concat();
+    assert_eq!(Decoder::new(&extra)?.decode_to_vec()?, data);
+    let res = Decoder::new(&extra)?.strict().decode_to_vec();
+    assert!(matches!(res, Err(Error::TrailingBytes { size: 1 })));
+
+    // strict mode overrides the individual options
+    let missing = &encoded[..encoded.len() - 3];
+    let mut decoder = Decoder::new(missing)?.with_allow_missing_padding(true);
+    assert_eq!(decoder.decode_to_vec()?, data);
+    let res = Decoder::new(missing)?.with_allow_missing_padding(true).strict().decode_to_vec();
+    assert!(matches!(res, Err(Error::UnexpectedBufferEnd)));
+    Ok(())
+}
+
+#[test]
+fn test_decode_progressive() -> Result<()> {
+    let (w, h) = (13, 9);
+    for channels in [3, 4] {
+        let img = gen_image(w, h, channels);
+        let encoded = encode_to_vec(&img, w, h)?;
+        let mut decoder = ProgressiveDecoder::new();
+        let mut prev = 0;
+        for n in 0..=encoded.len() {
+            let (n_pixels, complete) = decoder.decode_available(&encoded[..n])?;
+            assert!(n_pixels >= prev);
+            assert_eq!(complete, n == encoded.len());
+            assert_eq!(decoder.pixels(), &img[..n_pixels * channels]);
+            prev = n_pixels;
+        }
+        assert_eq!(prev, (w * h) as usize);
+        assert_eq!(decoder.header().map(|h| h.channels.as_u8()), Some(channels as u8));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_repair_dimensions() -> Result<()> {
+    let (w, h) = (11, 6);
+    let img = gen_image(w, h, 4);
+    let encoded = encode_to_vec(&img, w, h)?;
+    let mut corrupted = encoded.clone();
+    corrupted[4..8].copy_from_slice(&22_u32.to_be_bytes());
+    corrupted[8..12].copy_from_slice(&1000_u32.to_be_bytes());
+    assert!(decode_to_vec(&corrupted).is_err());
+    let repaired = repair_dimensions(&corrupted, w)?;
+    assert_eq!(repaired, encoded);
+    assert_eq!(decode_to_vec(&repaired)?.1, img);
+    assert!(matches!(
+        repair_dimensions(&corrupted, 4),
+        Err(Error::IndivisiblePixelCount { n_pixels: 66, width: 4 })
+    ));
+    assert!(matches!(repair_dimensions(&corrupted[..20], w), Err(Error::InvalidPadding)));
+    Ok(())
+}
+
+#[test]
+fn test_count_ops() -> Result<()> {
+    let mut data = b"qoif\0\0\0\x05\0\0\0\x01\x03\0".to_vec();
+    data.extend([0xfe, 10, 20, 30]); // rgb
+    data.extend([0xc1]); // run of 2
+    data.extend([0x80 | 33, 0x88]); // luma
+    data.extend([0x40 | 0x2a]); // diff
+    data.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(count_ops(&data)?, 4);
+    assert_eq!(decode_to_vec(&data)?.0.n_pixels(), 5);
+    assert!(matches!(count_ops(&data[..data.len() - 1]), Err(Error::UnexpectedBufferEnd)));
+    data[11] = 2;
+    assert!(count_ops(&data).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_decode_colorspace_clamp() -> Result<()> {
+    let data = gen_image(4, 3, 3);
+    let mut encoded = encode_to_vec(&data, 4, 3)?;
+    encoded[13] = 5;
+    assert!(matches!(decode_to_vec(&encoded), Err(Error::InvalidColorSpace { colorspace: 5 })));
+    let res = Decoder::new(&encoded);
+    assert!(matches!(res, Err(Error::InvalidColorSpace { colorspace: 5 })));
+    let res = Decoder::from_stream(&encoded[..]);
+    assert!(matches!(res, Err(Error::InvalidColorSpace { colorspace: 5 })));
+    let res = Decoder::new_lenient(&encoded)?.decode_to_vec();
+    assert!(matches!(res, Err(Error::InvalidColorSpace { colorspace: 5 })));
+    for mut decoder in [
+        Decoder::new_lenient(&encoded)?.with_colorspace_clamp(true),
+        Decoder::new_lenient(&encoded)?.with_colorspace_clamp(true).with_channels(Channels::Rgb),
+    ] {
+        assert_eq!(decoder.header().colorspace, ColorSpace::Linear);
+        assert_eq!(decoder.decode_to_vec()?, data);
+    }
+    let mut decoder = Decoder::from_stream_lenient(&encoded[..])?.with_colorspace_clamp(true);
+    assert_eq!(decoder.decode_to_vec()?, data);
+    let res = Decoder::new_lenient(&encoded)?.with_colorspace_clamp(true).strict().decode_to_vec();
+    assert!(matches!(res, Err(Error::InvalidColorSpace { colorspace: 5 })));
+
+    encoded[13] = 0;
+    let mut decoder = Decoder::new(&encoded)?.with_colorspace_clamp(true);
+    assert_eq!(decoder.header().colorspace, ColorSpace::Srgb);
+    assert_eq!(decoder.decode_to_vec()?, data);
+    Ok(())
+}
+
+#[test]
+fn test_decode_to_vec_with_index() -> Result<()> {
+    let mut data = b"qoif\0\0\0\x03\0\0\0\x01\x04\0".to_vec();
+    data.extend([0xfe, 10, 20, 30]); // hashed into slot 9
+    data.extend([0xff, 1, 2, 3, 4]); // hashed into slot 14
+    data.extend([0x09]); // index
+    data.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+    for channels in [Channels::Rgba, Channels::Rgb] {
+        let mut decoder = Decoder::new(&data)?.with_channels(channels);
+        let (header, pixels, index) = decoder.decode_to_vec_with_index()?;
+        assert_eq!(header.n_pixels(), 3);
+        assert_eq!(pixels.len(), 3 * channels.bytes_per_pixel());
+        for (i, px) in index.iter().enumerate() {
+            let expected = match i {
+                9 => [10, 20, 30, 255],
+                14 => [1, 2, 3, 4],
+                _ => [0; 4],
+            };
+            assert_eq!(*px, expected, "slot {i}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_infer_channels() -> Result<()> {
+    let (width, height) = (7, 5);
+    for channels in [3, 4] {
+        let data = gen_image(width, height, channels);
+        let mut encoded = encode_to_vec(&data, width, height)?;
+        for invalid in [0, 2, 5] {
+            encoded[12] = invalid;
+            let res = Decoder::new(&encoded);
+            assert!(matches!(res, Err(Error::InvalidChannels { channels }) if channels == invalid));
+            let res = Decoder::from_stream(&encoded[..]);
+            assert!(matches!(res, Err(Error::InvalidChannels { channels }) if channels == invalid));
+            let res = Decoder::new_lenient(&encoded)?.decode_to_vec();
+            assert!(matches!(res, Err(Error::InvalidChannels { channels }) if channels == invalid));
+            let mut decoder = Decoder::new_lenient(&encoded)?.with_infer_channels(true);
+            assert_eq!(decoder.header().channels.as_u8() as usize, channels);
+            assert_eq!(decoder.channels().as_u8() as usize, channels);
+            assert_eq!(decoder.decode_to_vec()?, data);
+
+            // streams can't be scanned in advance
+            let decoder = Decoder::from_stream_lenient(&encoded[..])?;
+            let res = decoder.with_infer_channels(true).decode_to_vec();
+            assert!(matches!(res, Err(Error::InvalidChannels { .. })));
+            // neither can truncated images
+            let truncated = &encoded[..encoded.len() - 1];
+            let res = Decoder::new_lenient(truncated)?.with_infer_channels(true).decode_to_vec();
+            assert!(matches!(res, Err(Error::InvalidChannels { .. })));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_scanline() -> Result<()> {
+    let (width, height) = (9, 4);
+    for channels in [3, 4] {
+        let data = gen_image(width, height, channels);
+        let encoded = encode_to_vec(&data, width, height)?;
+        let row_len = width as usize * channels;
+        for row in 0..height as usize {
+            assert_eq!(decode_scanline(&encoded, row)?, data[row * row_len..][..row_len]);
+        }
+        let res = decode_scanline(&encoded, 4);
+        assert!(matches!(res, Err(Error::RowOutOfBounds { row: 4, height: 4 })));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_to_rgb565_vec() -> Result<()> {
+    let pixels = [[255, 255, 255, 255], [0, 0, 0, 0], [128, 128, 128, 7], [0x12, 0x34, 0x56, 9]];
+    let encoded = encode_to_vec(pixels.concat(), 2, 2)?;
+    let (header, out) = Decoder::new(&encoded)?.decode_to_rgb565_vec()?;
+    assert_eq!((header.width, header.height, header.channels), (2, 2, Channels::Rgba));
+    let expected: Vec<u8> =
+        [0xffff_u16, 0x0000, 0x8410, 0x11aa].iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(out, expected);
+    let (_, out) = Decoder::new(&encoded)?.with_channels(Channels::Rgb).decode_to_rgb565_vec()?;
+    assert_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn test_decode_with_context() -> Result<()> {
+    let mut ctx = Box::new(DecodeContext::new());
+    for (width, height, channels) in [(13, 7, 3), (5, 9, 4), (1, 1, 4)] {
+        let data = gen_image(width, height, channels);
+        let encoded = encode_to_vec(&data, width, height)?;
+        let mut buf = vec![0; data.len()];
+        let header = decode_with_context(&mut ctx, &mut buf, &encoded)?;
+        assert_eq!((header.width, header.height), (width, height));
+        assert_eq!(buf, decode_to_vec(&encoded)?.1);
+
+        let res = decode_with_context(&mut ctx, &mut buf[1..], &encoded);
+        assert!(matches!(res, Err(Error::OutputBufferTooSmall { .. })));
+        let res = decode_with_context(&mut ctx, &mut buf, &encoded[..encoded.len() - 1]);
+        assert!(matches!(res, Err(Error::UnexpectedBufferEnd)));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_to_buf_aligned() -> Result<()> {
+    let (width, height) = (5, 3);
+    let data = gen_image(width, height, 4);
+    let encoded = encode_to_vec(&data, width, height)?;
+    let row_len = 5 * 4;
+    let required = 256 * 2 + row_len;
+
+    let mut buf = vec![0xaa; required];
+    assert_eq!(Decoder::new(&encoded)?.decode_to_buf_aligned(&mut buf, 256)?, 256);
+    for (y, row) in data.chunks(row_len).enumerate() {
+        assert_eq!(buf[y * 256..][..row_len], *row);
+    }
+    assert!(buf[row_len..256].iter().all(|&b| b == 0xaa));
+
+    let mut rgb = vec![0; 16 * 2 + 15];
+    let stride =
+        Decoder::new(&encoded)?.with_channels(Channels::Rgb).decode_to_buf_aligned(&mut rgb, 16)?;
+    assert_eq!(stride, 16);
+    assert_eq!(
+        rgb[16..][..15],
+        data[20..40].chunks(4).flat_map(|px| &px[..3]).copied().collect::<Vec<_>>()[..]
+    );
+
+    // zero alignment is the same as tightly packed
+    let mut packed = vec![0; data.len()];
+    assert_eq!(Decoder::new(&encoded)?.decode_to_buf_aligned(&mut packed, 0)?, row_len);
+    assert_eq!(packed, data);
+
+    assert!(matches!(
+        Decoder::new(&encoded)?.decode_to_buf_aligned(&mut buf[1..], 256),
+        Err(Error::OutputBufferTooSmall { size, required: 532 }) if size == required - 1
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_decode_enumerate_pixels() -> Result<()> {
+    let (width, height) = (7, 5);
+    let data = gen_image(width, height, 3);
+    let encoded = encode_to_vec(&data, width, height)?;
+    let pixels = Decoder::new(&encoded)?.enumerate_pixels().collect::<Result<Vec<_>>>()?;
+    assert_eq!(pixels.len(), 35);
+    assert_eq!(pixels.last().map(|&(x, y, _)| (x, y)), Some((width - 1, height - 1)));
+    for (i, &(x, y, px)) in pixels.iter().enumerate() {
+        assert_eq!((x as usize, y as usize), (i % 7, i / 7));
+        assert_eq!(px, [data[i * 3], data[i * 3 + 1], data[i * 3 + 2], 0xff]);
+    }
+
+    // coordinates continue from where a partial decode has stopped
+    let mut decoder = Decoder::new(&encoded)?;
+    decoder.decode_partial(10)?;
+    assert_eq!(decoder.enumerate_pixels().next().transpose()?, Some(pixels[10]));
+
+    let mut truncated = encoded.clone();
+    truncated.pop();
+    let mut decoder = Decoder::new(&truncated)?;
+    let results = decoder.enumerate_pixels().collect::<Vec<_>>();
+    assert_eq!(results.len(), 35);
+    assert!(results[34].is_err());
+    Ok(())
+}
+
+#[test]
+fn test_decode_with_integer_scale() -> Result<()> {
+    let (a, b, c, d) = ([1, 2, 3], [4, 5, 6], [7, 8, 9], [10, 11, 12]);
+    let encoded = encode_to_vec([a, b, c, d].concat(), 2, 2)?;
+    let mut decoder = Decoder::new(&encoded)?.with_integer_scale(2);
+    assert_eq!(decoder.required_buf_len(), 4 * 4 * 3);
+    let expected = [[a, a, b, b], [a, a, b, b], [c, c, d, d], [c, c, d, d]].concat().concat();
+    assert_eq!(decoder.decode_to_vec()?, expected);
+    assert_eq!(decoder.header().width, 2);
+
+    let mut buf = vec![0; 4 * 4 * 4 + 1];
+    let decoder =
+        || Decoder::new(&encoded).map(|d| d.with_integer_scale(2).with_channels(Channels::Rgba));
+    assert_eq!(decoder()?.decode_to_buf(&mut buf)?, 64);
+    assert_eq!(buf[4 * 4 * 3..][..8], [7, 8, 9, 255, 7, 8, 9, 255]);
+    assert!(matches!(
+        decoder()?.decode_to_buf(&mut buf[..63]),
+        Err(Error::OutputBufferTooSmall { size: 63, required: 64 })
+    ));
+
+    let tripled = Decoder::new(&encoded)?.with_integer_scale(3).decode_to_vec()?;
+    assert_eq!(tripled.len(), 6 * 6 * 3);
+    assert_eq!(tripled[6 * 5 * 3..], [c, c, c, d, d, d].concat());
+    for factor in [0, 1] {
+        let decoded = Decoder::new(&encoded)?.with_integer_scale(factor).decode_to_vec()?;
+        assert_eq!(decoded, [a, b, c, d].concat());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_skip_trailing_chunks() -> Result<()> {
+    let data = gen_image(4, 3, 4);
+    let chunk = b"EXT0\x00\x00\x00\x02hi";
+    let encoded = [&encode_to_vec(&data, 4, 3)?[..], chunk].concat();
+
+    let mut decoder = Decoder::new(&encoded)?.strict();
+    assert!(matches!(decoder.decode_to_vec(), Err(Error::TrailingBytes { size: 10 })));
+    for decoder in [
+        Decoder::new(&encoded)?.strict().with_skip_trailing_chunks(true),
+        Decoder::new(&encoded)?.with_skip_trailing_chunks(true).strict(),
+    ] {
+        let mut decoder = decoder;
+        assert!(decoder.trailing_bytes().is_empty());
+        assert_eq!(decoder.decode_to_vec()?, data);
+        assert_eq!(decoder.trailing_bytes(), chunk);
+    }
+    // the rest of the validation still applies
+    let mut truncated = encoded.clone();
+    truncated.remove(encoded.len() - chunk.len() - 1);
+    let mut decoder = Decoder::new(&truncated)?.strict().with_skip_trailing_chunks(true);
+    assert!(decoder.decode_to_vec().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_decode_with_error_fill() -> Result<()> {
+    let (width, height) = (5, 4);
+    let data = gen_image(width, height, 4);
+    let encoded = Encoder::new(&data, width, height)?.with_raw_ops(true).encode_to_vec()?;
+    let fill = [1, 2, 3, 4];
+    // 7 complete QOI_OP_RGBA ops followed by a partial one
+    let truncated = &encoded[..14 + 7 * 5 + 2];
+    assert!(Decoder::new(truncated)?.decode_to_vec().is_err());
+
+    let mut expected = data[..7 * 4].to_vec();
+    expected.extend(fill.repeat(13));
+    let mut decoder = Decoder::new(truncated)?.with_error_fill(fill);
+    assert!(!decoder.recovered());
+    assert_eq!(decoder.decode_to_vec()?, expected);
+    assert!(decoder.recovered());
+    assert!(decoder.trailing_bytes().is_empty());
+    let mut decoder = Decoder::from_stream(truncated)?.with_error_fill(fill);
+    assert_eq!(decoder.decode_to_vec()?, expected);
+    assert!(decoder.recovered());
+
+    let rgb = expected.chunks(4).flat_map(|px| px[..3].to_vec()).collect::<Vec<_>>();
+    let mut decoder = Decoder::new(truncated)?.with_channels(Channels::Rgb).with_error_fill(fill);
+    assert_eq!(decoder.decode_to_vec()?, rgb);
+
+    // a run past the end of the image
+    let mut overflow = encode_to_vec([0; 6], 2, 1)?;
+    overflow[14] += 2;
+    assert!(matches!(Decoder::new(&overflow)?.decode_to_vec(), Err(Error::RunOverflow)));
+    let mut decoder = Decoder::new(&overflow)?.with_error_fill(fill);
+    assert_eq!(decoder.decode_to_vec()?, [0; 6]);
+    assert!(decoder.recovered());
+
+    // intact images decode as usual
+    let mut decoder = Decoder::new(&encoded)?.with_error_fill(fill);
+    assert_eq!(decoder.decode_to_vec()?, data);
+    assert!(!decoder.recovered());
+    Ok(())
+}
+
+#[test]
+fn test_op_length() -> Result<()> {
+    for b1 in 0..=255_u8 {
+        let expected = match b1 {
+            0x00..=0x3f => 1, // QOI_OP_INDEX
+            0x40..=0x7f => 1, // QOI_OP_DIFF
+            0x80..=0xbf => 2, // QOI_OP_LUMA
+            0xc0..=0xfd => 1, // QOI_OP_RUN
+            0xfe => 4,        // QOI_OP_RGB
+            0xff => 5,        // QOI_OP_RGBA
+        };
+        assert_eq!(op_length(b1), expected, "{b1:#04x}");
+    }
+
+    // walking a real image op by op ends up right at the padding
+    let data = gen_image(13, 11, 4);
+    let encoded = encode_to_vec(&data, 13, 11)?;
+    let ops = &encoded[14..encoded.len() - 8];
+    let (mut offset, mut n_ops) = (0, 0);
+    while offset < ops.len() {
+        offset += op_length(ops[offset]);
+        n_ops += 1;
+    }
+    assert_eq!(offset, ops.len());
+    assert_eq!(n_ops, count_ops(&encoded)?);
+    Ok(())
+}
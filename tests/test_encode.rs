@@ -0,0 +1,895 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
+use std::sync::Arc;
+use std::thread;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use qoi::consts::{
+    QOI_HEADER_SIZE, QOI_OP_DIFF, QOI_OP_INDEX, QOI_OP_LUMA, QOI_OP_RGB, QOI_OP_RGBA, QOI_OP_RUN,
+    QOI_PADDING,
+};
+use qoi::{
+    decode_to_vec, decode_to_vec_prewarmed, decode_to_vec_with_hash, encode_canonical,
+    encode_fn_to_stream, encode_max_len, encode_one_op, encode_solid, encode_to_vec,
+    read_trailer_metadata, theoretical_min_size, BufferPool, Channels, CompatMode, ComponentOrder,
+    EncodeStats, Encoder, Error, Layout, OpKind, RawChannels, Result, StandardHash, StreamEncoder,
+    WeightedHash, STREAM_FLUSH_CHUNK,
+};
+
+#[test]
+fn test_encode_max_len_worst_case() -> Result<()> {
+    // every pixel has a new alpha and a new color, so each one is encoded as QOI_OP_RGBA
+    let n = 200_u32;
+    let data = (0..n as u8).flat_map(|i| [i, 255 - i, i ^ 0x55, i]).collect::<Vec<_>>();
+    let encoded = encode_to_vec(&data, n, 1)?;
+    assert_eq!(encoded.len(), encode_max_len(n, 1, Channels::Rgba));
+    assert_eq!(encode_max_len(n, 1, 0), encode_max_len(n, 1, Channels::Rgba));
+    assert_eq!(encode_max_len(n, 1, 0xff), encode_max_len(n, 1, Channels::Rgba));
+    assert!(encode_max_len(n, 1, Channels::Rgb) < encode_max_len(n, 1, Channels::Rgba));
+    Ok(())
+}
+
+#[test]
+fn test_encode_from_planes() -> Result<()> {
+    let (width, height) = (5, 3);
+    let n = (width * height) as usize;
+    let plane = |k: usize| (0..n).map(|i| (i * k / 3) as u8).collect::<Vec<_>>();
+    let (r, g, b, a) = (plane(1), plane(7), plane(13), plane(29));
+
+    let rgb = (0..n).flat_map(|i| [r[i], g[i], b[i]]).collect::<Vec<_>>();
+    let encoded = Encoder::from_planes(&r, &g, &b, None, width, height)?.encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(&rgb, width, height)?);
+    assert_eq!(decode_to_vec(&encoded)?.0.channels, Channels::Rgb);
+
+    let rgba = (0..n).flat_map(|i| [r[i], g[i], b[i], a[i]]).collect::<Vec<_>>();
+    let encoded = Encoder::from_planes(&r, &g, &b, Some(&a), width, height)?.encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(&rgba, width, height)?);
+    assert_eq!(decode_to_vec(&encoded)?.1, rgba);
+
+    let res = Encoder::from_planes(&r, &g[1..], &b, None, width, height);
+    assert!(matches!(res, Err(Error::InvalidImageLength { size: 14, width: 5, height: 3 })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_within() -> Result<()> {
+    let data = (0..64 * 64 * 3).map(|i| (i * 31 % 251) as u8).collect::<Vec<_>>();
+    let encoder = Encoder::new(&data, 64, 64)?;
+    let expected = encoder.encode_to_vec()?;
+    assert_eq!(encoder.encoded_len()?, expected.len());
+    assert_eq!(encoder.encode_within(expected.len())?, expected);
+    assert_eq!(encoder.encode_within(usize::MAX)?, expected);
+    let res = encoder.encode_within(4096);
+    assert!(
+        matches!(res, Err(Error::SizeBudgetExceeded { size, budget: 4096 }) if size == expected.len())
+    );
+
+    let flat = vec![0x42; 64 * 64 * 3];
+    assert!(Encoder::new(&flat, 64, 64)?.encode_within(4096)?.len() <= 4096);
+    Ok(())
+}
+
+#[test]
+fn test_encode_with_trace() -> Result<()> {
+    let trace_of = |data: &[u8], width| -> Result<Vec<(usize, OpKind)>> {
+        let encoder = Encoder::new(data, width, 1)?;
+        let mut trace = Vec::new();
+        let mut buf = vec![0; encoder.required_buf_len()];
+        let size = encoder.encode_with_trace(&mut buf, |i, op| trace.push((i, op)))?;
+        assert_eq!(buf[..size], encoder.encode_to_vec()?);
+        Ok(trace)
+    };
+
+    let (a, b) = ([10, 20, 30], [11, 21, 31]);
+    let data = [a, a, a, b, a, a].concat();
+    let expected = [
+        (0, OpKind::Rgb),
+        (1, OpKind::Run),
+        (3, OpKind::Diff),
+        (4, OpKind::Index),
+        (5, OpKind::Run),
+    ];
+    assert_eq!(trace_of(&data, 6)?, expected);
+
+    let data = [[0, 0, 0, 255], [40, 50, 60, 255], [45, 60, 70, 255], [45, 60, 70, 7]].concat();
+    let expected = [(0, OpKind::Run), (1, OpKind::Rgb), (2, OpKind::Luma), (3, OpKind::Rgba)];
+    assert_eq!(trace_of(&data, 4)?, expected);
+    Ok(())
+}
+
+#[test]
+fn test_encode_stats_runs() -> Result<()> {
+    let data = vec![100; 200 * 3];
+    let encoder = Encoder::new(&data, 20, 10)?;
+    let stats = encoder.encode_stats()?;
+    let expected = EncodeStats {
+        size: encoder.encoded_len()?,
+        num_rgb: 1,
+        num_run: 4,
+        max_run: 62,
+        num_runs_at_cap: 3,
+        ..EncodeStats::default()
+    };
+    assert_eq!(stats, expected);
+
+    let data = vec![0; 100 * 3];
+    let stats = Encoder::new(&data, 10, 10)?.encode_stats()?;
+    assert_eq!((stats.num_run, stats.max_run, stats.num_runs_at_cap), (2, 62, 1));
+
+    let data = [[1, 2, 3], [1, 2, 3], [1, 2, 3], [9, 9, 9], [9, 9, 9]].concat();
+    let stats = Encoder::new(&data, 5, 1)?.encode_stats()?;
+    assert_eq!((stats.num_run, stats.max_run, stats.num_runs_at_cap), (2, 2, 0));
+    Ok(())
+}
+
+#[test]
+fn test_stream_encoder() -> Result<()> {
+    for channels in [3, 4] {
+        let (width, height) = (17, 9);
+        let data = (0..width * height * channels)
+            .map(|i| if i % 61 < 40 { 7 } else { (i * 37 % 253) as u8 })
+            .collect::<Vec<_>>();
+        let mut encoder =
+            StreamEncoder::new(width as _, height as _, (channels as u8).try_into()?)?;
+        let mut out = Vec::new();
+        for (i, px) in data.chunks_exact(channels).enumerate() {
+            encoder.push([px[0], px[1], px[2], px.get(3).copied().unwrap_or(0)])?;
+            if i % 10 == 0 {
+                out.extend(encoder.take_output());
+            }
+        }
+        assert!(encoder.clone().push([0; 4]).is_err());
+        out.extend(encoder.finish()?);
+        assert_eq!(out, encode_to_vec(&data, width as _, height as _)?);
+    }
+
+    let mut encoder = StreamEncoder::new(2, 1, Channels::Rgb)?;
+    encoder.push([1, 2, 3, 4])?;
+    assert!(matches!(encoder.finish(), Err(Error::InvalidImageLength { size: 3, .. })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_unpremultiply() -> Result<()> {
+    let premul = [64, 32, 128, 128, 10, 20, 30, 0, 200, 100, 50, 255];
+    let straight = [128, 64, 255, 128, 10, 20, 30, 0, 200, 100, 50, 255];
+    let encoded = Encoder::new(&premul, 3, 1)?.with_unpremultiply(true).encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(straight, 3, 1)?);
+    let (_, decoded) = decode_to_vec(&encoded)?;
+    assert_eq!(decoded, straight);
+
+    let rgb = [64, 32, 128, 1, 2, 3];
+    let encoded = Encoder::new(&rgb, 2, 1)?.with_unpremultiply(true).encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(rgb, 2, 1)?);
+    Ok(())
+}
+
+#[test]
+fn test_encode_from_pixels() -> Result<()> {
+    let rgba = (0..12_u8).map(|i| [i, i * 2, i * 3, 255 - i]).collect::<Vec<_>>();
+    let encoded = Encoder::from_rgba_pixels(&rgba, 4, 3)?.encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(rgba.concat(), 4, 3)?);
+    let rgb = rgba.iter().map(|&[r, g, b, _]| [r, g, b]).collect::<Vec<_>>();
+    let encoded = Encoder::from_rgb_pixels(&rgb, 3, 4)?.encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(rgb.concat(), 3, 4)?);
+
+    let res = Encoder::from_rgba_pixels(&rgba, 4, 4);
+    assert!(matches!(res, Err(Error::InvalidImageLength { size: 48, width: 4, height: 4 })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_byte_iter() -> Result<()> {
+    let (width, height) = (17, 9);
+    for channels in [3, 4] {
+        let data =
+            (0..width * height * channels).map(|i| (i % 7 * 40 + i / 50) as u8).collect::<Vec<_>>();
+        let encoder = Encoder::new(&data, width as _, height as _)?;
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoder.encode_to_vec()?);
+        let encoder = encoder.with_unpremultiply(true);
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoder.encode_to_vec()?);
+    }
+    let (r, g, b) = ([1, 2, 3, 3], [4, 5, 6, 6], [7, 8, 9, 9]);
+    let encoder = Encoder::from_planes(&r, &g, &b, None, 2, 2)?;
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoder.encode_to_vec()?);
+    Ok(())
+}
+
+#[test]
+fn test_encode_raw_ops() -> Result<()> {
+    let (width, height) = (7, 5);
+    for channels in [3, 4] {
+        let data = [[10, 20, 30, 40], [10, 20, 30, 40], [11, 21, 31, 40]]
+            .iter()
+            .flat_map(|px| &px[..channels])
+            .copied()
+            .cycle()
+            .take(width * height * channels)
+            .collect::<Vec<u8>>();
+        let encoder = Encoder::new(&data, width as _, height as _)?.with_raw_ops(true);
+        let encoded = encoder.encode_to_vec()?;
+        assert_eq!(encoded.len(), 14 + width * height * (channels + 1) + 8);
+        assert_eq!(encoded.len(), encode_max_len(width as _, height as _, channels as u8));
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+        assert_eq!(encoder.encode_stats()?.num_index, 0);
+        assert_eq!(decode_to_vec(&encoded)?.1, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_disable_index() -> Result<()> {
+    let (width, height) = (16, 16);
+    let colors = [[10, 20, 30, 255], [200, 100, 0, 255], [10, 20, 30, 255], [0, 0, 0, 0]];
+    let data = (0..width * height).flat_map(|i| colors[i % 7 % 4]).collect::<Vec<u8>>();
+    let encoder = Encoder::new(&data, width as _, height as _)?;
+    assert!(encoder.encode_stats()?.num_index > 0);
+    let encoder = encoder.with_disable_index(true);
+    assert_eq!(encoder.encode_stats()?.num_index, 0);
+    let mut kinds = Vec::new();
+    encoder.encode_with_trace(vec![0; encoder.required_buf_len()], |_, kind| kinds.push(kind))?;
+    assert!(!kinds.is_empty() && !kinds.contains(&OpKind::Index));
+    let encoded = encoder.encode_to_vec()?;
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+    assert_eq!(decode_to_vec(&encoded)?.1, data);
+    Ok(())
+}
+
+#[test]
+fn test_encode_bit_reduction() -> Result<()> {
+    let (width, height) = (32, 32);
+    // a coarse gradient with noise in the low bits
+    let data = (0..width * height * 4)
+        .map(|i| if i % 4 == 3 { 0xff } else { ((i / 64) as u8 & 0xf0) | (i * 37 % 16) as u8 })
+        .collect::<Vec<_>>();
+    let encoder = Encoder::new(&data, width, height)?;
+    let full = encoder.encode_to_vec()?;
+    assert_eq!(Encoder::new(&data, width, height)?.with_bit_reduction(8).encode_to_vec()?, full);
+
+    let encoder = Encoder::new(&data, width, height)?.with_bit_reduction(4);
+    let reduced = encoder.encode_to_vec()?;
+    assert!(reduced.len() < full.len());
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), reduced);
+    let quantized = data.iter().map(|&c| c & 0xf0).collect::<Vec<_>>();
+    assert_eq!(decode_to_vec(&reduced)?.1, quantized);
+
+    let zeroed = Encoder::new(&data, width, height)?.with_bit_reduction(0).encode_to_vec()?;
+    assert_eq!(decode_to_vec(zeroed)?.1, vec![0; data.len()]);
+
+    // the implicit alpha of RGB images isn't affected
+    let rgb = data.chunks(4).flat_map(|px| px[..3].to_vec()).collect::<Vec<_>>();
+    for bits in [0, 4, 7] {
+        let encoder = Encoder::new(&rgb, width, height)?.with_bit_reduction(bits);
+        let reduced = encoder.encode_to_vec()?;
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), reduced);
+        let mask = (0xff_u16 << (8 - bits)) as u8;
+        assert_eq!(decode_to_vec(&reduced)?.1, rgb.iter().map(|&c| c & mask).collect::<Vec<_>>());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_parts() -> Result<()> {
+    let (width, height) = (7, 5);
+    let data = (0..width * height * 3).map(|i| (i * 13 / 5) as u8).collect::<Vec<_>>();
+    let encoder = Encoder::new(&data, width, height)?;
+    let (header, body, padding) = encoder.encode_parts()?;
+    assert_eq!(header.len(), 14);
+    assert_eq!(padding, [0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!([header, body, padding.to_vec()].concat(), encoder.encode_to_vec()?);
+    Ok(())
+}
+
+#[test]
+fn test_encode_wrapping_diffs() -> Result<()> {
+    // differences wrap around 0/255, so e.g. 255 -> 0 is +1 and 1 -> 255 is -2
+    let cases = [
+        ([255, 1, 0], [0, 255, 254], OpKind::Diff),
+        ([1, 255, 255], [255, 0, 0], OpKind::Diff),
+        ([0, 0, 1], [255, 254, 255], OpKind::Diff),
+        // +2 is out of the DIFF range, the next smallest op is LUMA
+        ([255, 255, 255], [1, 1, 1], OpKind::Luma),
+        ([250, 240, 230], [20, 10, 0], OpKind::Luma),
+        ([240, 250, 0], [0, 6, 4], OpKind::Luma),
+    ];
+    for (first, second, expected) in cases {
+        let data = [first, second].concat();
+        let encoder = Encoder::new(&data, 2, 1)?;
+        let mut buf = vec![0; encoder.required_buf_len()];
+        let mut kinds = Vec::new();
+        let n = encoder.encode_with_trace(&mut buf, |i, kind| kinds.push((i, kind)))?;
+        assert_eq!(kinds.last(), Some(&(1, expected)), "{first:?} -> {second:?}");
+        assert_eq!(decode_to_vec(&buf[..n])?.1, data);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_solid() -> Result<()> {
+    let colors = [[0, 0, 0, 255], [0, 0, 0, 0], [1, 255, 0, 255], [10, 200, 30, 40]];
+    for color in colors {
+        for (width, height) in [(1, 1), (2, 1), (62, 1), (63, 1), (31, 4), (100, 37)] {
+            for channels in [Channels::Rgb, Channels::Rgba] {
+                let encoded = encode_solid(color, width, height, channels)?;
+                let px = &color[..channels.bytes_per_pixel()];
+                let data = px.repeat((width * height) as usize);
+                assert_eq!(encoded, encode_to_vec(&data, width, height)?);
+                let (header, decoded) = decode_to_vec(&encoded)?;
+                assert_eq!(
+                    (header.width, header.height, header.channels),
+                    (width, height, channels)
+                );
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+    assert!(matches!(
+        encode_solid([0; 4], 0, 1, Channels::Rgb),
+        Err(Error::InvalidImageDimensions { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_encode_trailer_metadata() -> Result<()> {
+    let (width, height) = (9, 4);
+    let data = (0..width * height * 4).map(|i| (i * 3 / 7) as u8).collect::<Vec<_>>();
+    let plain = encode_to_vec(&data, width, height)?;
+    assert_eq!(read_trailer_metadata(&plain), None);
+
+    for metadata in [&b"origin: test-pipeline"[..], b"", &[0xaa; 255]] {
+        let encoder = Encoder::new(&data, width, height)?.with_trailer_metadata(metadata)?;
+        let encoded = encoder.encode_to_vec()?;
+        assert_eq!(encoded.len(), plain.len() + 1 + metadata.len());
+        assert_eq!(encoded[..plain.len()], plain);
+        assert_eq!(encoder.encoded_len()?, encoded.len());
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+        let mut streamed = Vec::new();
+        assert_eq!(encoder.encode_to_stream(&mut streamed)?, encoded.len());
+        assert_eq!(streamed, encoded);
+        assert_eq!(read_trailer_metadata(&encoded), Some(metadata));
+        assert_eq!(decode_to_vec(&encoded)?.1, data);
+        // a truncated trailer is not recognized
+        assert_eq!(read_trailer_metadata(&encoded[..encoded.len() - 1]), None);
+    }
+
+    let res = Encoder::new(&data, width, height)?.with_trailer_metadata(&[0; 256]);
+    assert!(matches!(res, Err(Error::TrailerTooLong { size: 256 })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_row_sizes() -> Result<()> {
+    // the top half is a flat color, the bottom half is noise
+    let (width, height) = (64_u32, 8_u32);
+    let mut rng = StdRng::seed_from_u64(0);
+    let data = (0..width * height)
+        .flat_map(|i| if i < width * height / 2 { [10, 20, 30] } else { rng.gen() })
+        .collect::<Vec<u8>>();
+    let encoder = Encoder::new(&data, width, height)?;
+    let sizes = encoder.row_sizes()?;
+    assert_eq!(sizes.len(), height as usize);
+    assert_eq!(sizes.iter().sum::<usize>() + 14 + 8, encoder.encoded_len()?);
+    assert!(sizes[1..4].iter().all(|&size| size <= 2), "{sizes:?}");
+    assert!(sizes[4..].iter().all(|&size| size > width as usize * 2), "{sizes:?}");
+    Ok(())
+}
+
+#[test]
+fn test_encode_from_indexed() -> Result<()> {
+    let palette = [[0, 0, 0, 255], [255, 0, 0, 255], [0, 128, 255, 100], [7, 7, 7, 0]];
+    let (width, height) = (12, 5);
+    let indices = (0..width * height).map(|i| (i * i / 7 % 4) as u8).collect::<Vec<_>>();
+    let expanded = indices.iter().flat_map(|&i| palette[i as usize]).collect::<Vec<_>>();
+    let encoder = Encoder::from_indexed(&indices, &palette, width, height)?;
+    assert_eq!(encoder.channels(), Channels::Rgba);
+    let encoded = encoder.encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(&expanded, width, height)?);
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+    assert_eq!(decode_to_vec(&encoded)?.1, expanded);
+
+    let res = Encoder::from_indexed(&indices, &palette[..3], width, height);
+    assert!(matches!(res, Err(Error::InvalidPaletteIndex { index: 3, palette_len: 3 })));
+    let res = Encoder::from_indexed(&indices[1..], &palette, width, height);
+    assert!(matches!(res, Err(Error::InvalidImageLength { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_to_vec_with_layout() -> Result<()> {
+    let (width, height) = (10, 6);
+    let data = (0..width * height * 3).map(|i| (i / 4) as u8).collect::<Vec<_>>();
+    let encoder = Encoder::new(&data, width, height)?;
+    let (encoded, layout) = encoder.encode_to_vec_with_layout()?;
+    assert_eq!(encoded, encoder.encode_to_vec()?);
+    let (header, body, padding) = encoder.encode_parts()?;
+    let expected = Layout {
+        header_start: 0,
+        body_start: header.len(),
+        padding_start: header.len() + body.len(),
+        total_len: encoded.len(),
+    };
+    assert_eq!(layout, expected);
+    assert_eq!(encoded[layout.padding_start..], padding);
+
+    let encoder = encoder.with_trailer_metadata(b"meta")?;
+    let (encoded, with_trailer) = encoder.encode_to_vec_with_layout()?;
+    assert_eq!(with_trailer, Layout { total_len: encoded.len(), ..layout });
+    assert_eq!(encoded[with_trailer.padding_start..][..8], padding);
+    Ok(())
+}
+
+#[test]
+fn test_encode_canonical() -> Result<()> {
+    // a run of length 1 is encoded as QOI_OP_INDEX unless the reference algorithm is used
+    let data = [[10, 20, 30], [70, 80, 90], [70, 80, 90], [0, 0, 0]].concat();
+    let canonical = encode_canonical(&data, 4, 1)?;
+    assert_eq!(canonical[QOI_HEADER_SIZE + 8], QOI_OP_RUN);
+    assert_eq!(Encoder::new(&data, 4, 1)?.encode_canonical()?, canonical);
+    assert_eq!(decode_to_vec(&canonical)?.1, data);
+    let encoded = encode_to_vec(&data, 4, 1)?;
+    assert_eq!(encoded == canonical, cfg!(feature = "reference"));
+    assert_eq!(encoded.len(), canonical.len());
+    Ok(())
+}
+
+#[test]
+fn test_encode_column_major() -> Result<()> {
+    let (width, height) = (7_usize, 5_usize);
+    for channels in [Channels::Rgb, Channels::Rgba] {
+        let n = channels.bytes_per_pixel();
+        let row_major = (0..width * height * n).map(|i| (i * 37 % 251) as u8).collect::<Vec<_>>();
+        let mut column_major = vec![0; row_major.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let (src, dst) = ((y * width + x) * n, (x * height + y) * n);
+                column_major[dst..dst + n].copy_from_slice(&row_major[src..src + n]);
+            }
+        }
+        let encoder = Encoder::new_column_major(&column_major, 7, 5, channels)?;
+        let encoded = encoder.encode_to_vec()?;
+        assert_eq!(encoded, encode_to_vec(&row_major, 7, 5)?);
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+        assert_eq!(decode_to_vec(&encoded)?.1, row_major);
+    }
+    let res = Encoder::new_column_major(&[0; 10], 2, 2, Channels::Rgb);
+    assert!(matches!(res, Err(Error::InvalidImageLength { size: 10, width: 2, height: 2 })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_new_raw_validate() -> Result<()> {
+    // 3x2 RGB image with rows padded to 11 bytes, the last row is not padded
+    let row_major = (0..18).map(|i| i * 13).collect::<Vec<u8>>();
+    let mut strided = vec![0xee; 11 + 9];
+    strided[..9].copy_from_slice(&row_major[..9]);
+    strided[11..].copy_from_slice(&row_major[9..]);
+    let encoder = Encoder::new_raw(&strided, 3, 2, 11, Channels::Rgb)?;
+    encoder.validate()?;
+    let encoded = encoder.encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(&row_major, 3, 2)?);
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+    assert!(Encoder::new(&row_major, 3, 2)?.validate().is_ok());
+
+    // extra trailing rows are ignored
+    strided.extend([0xee; 11]);
+    assert_eq!(Encoder::new_raw(&strided, 3, 2, 11, Channels::Rgb)?.encode_to_vec()?, encoded);
+
+    let res = Encoder::new_raw(&strided, 3, 2, 8, Channels::Rgb);
+    assert!(matches!(res, Err(Error::InvalidStride { stride: 8, min_stride: 9 })));
+    let res = Encoder::new_raw(&strided, 3, 2, 11, Channels::Rgba);
+    assert!(matches!(res, Err(Error::InvalidStride { stride: 11, min_stride: 12 })));
+    let res = Encoder::new_raw(&strided[..19], 3, 2, 11, Channels::Rgb);
+    assert!(matches!(res, Err(Error::InvalidImageLength { size: 19, width: 3, height: 2 })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_new_raw_component_order() -> Result<()> {
+    use ComponentOrder::{Abgr, Argb, Bgr, Bgra, Rgb, Rgba};
+
+    let (width, height, stride) = (5, 3, 23);
+    let rgba = (0..width * height * 4).map(|i| (i * 29 % 251) as u8).collect::<Vec<_>>();
+    for order in [Rgb, Bgr, Rgba, Bgra, Argb, Abgr] {
+        let raw = RawChannels::with_order(order.channels(), order)?;
+        let n = raw.channels().bytes_per_pixel();
+        let mut strided = vec![0xee; stride * height as usize];
+        for (i, px) in rgba.chunks(4).enumerate() {
+            let offset = (i / width as usize) * stride + (i % width as usize) * n;
+            for (out, c) in strided[offset..][..n].iter_mut().zip(raw.pattern()) {
+                *out = c.pick(px.try_into().unwrap());
+            }
+        }
+        let expected = rgba.chunks(4).flat_map(|px| px[..n].to_vec()).collect::<Vec<_>>();
+        let expected = encode_to_vec(expected, width, height)?;
+
+        let encoder = Encoder::new_raw(&strided, width, height, stride, raw)?;
+        assert_eq!(encoder.encode_to_vec()?, expected, "{order:?}");
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), expected, "{order:?}");
+    }
+    let res = Encoder::new_raw(&rgba, width, height, 19, RawChannels::from(Channels::Rgba));
+    assert!(matches!(res, Err(Error::InvalidStride { stride: 19, min_stride: 20 })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_with_hash() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let palette = (0..20).map(|_| rng.gen::<[u8; 4]>()).collect::<Vec<_>>();
+    let data = (0..500).flat_map(|_| palette[rng.gen_range(0..20)]).collect::<Vec<_>>();
+    let encoder = Encoder::new(&data, 25, 20)?;
+
+    let standard = encoder.encode_to_vec_with_hash::<StandardHash>()?;
+    assert_eq!(standard, encoder.encode_to_vec()?);
+    assert_eq!(decode_to_vec_with_hash::<StandardHash>(&standard)?.1, data);
+
+    // the standard weights only differ in the magic bytes
+    let same = encoder.encode_to_vec_with_hash::<WeightedHash<3, 5, 7, 11>>()?;
+    assert_eq!(&same[..4], b"qoiw");
+    assert_eq!(same[4..], standard[4..]);
+
+    type Custom = WeightedHash<1, 2, 4, 8>;
+    let custom = encoder.encode_to_vec_with_hash::<Custom>()?;
+    assert_ne!(custom[4..], standard[4..]);
+    let (header, decoded) = decode_to_vec_with_hash::<Custom>(&custom)?;
+    assert_eq!((header.width, header.height, header.channels), (25, 20, Channels::Rgba));
+    assert_eq!(decoded, data);
+    assert!(matches!(decode_to_vec(&custom), Err(Error::InvalidMagic { .. })));
+    let res = decode_to_vec_with_hash::<StandardHash>(&custom);
+    assert!(matches!(res, Err(Error::InvalidMagic { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_encode_from_fn() -> Result<()> {
+    let checkerboard = |x: u32, y: u32| {
+        if (x / 4 + y / 4) % 2 == 0 {
+            [255, 255, 255, 255]
+        } else {
+            [0, 0, 0, 128]
+        }
+    };
+    for channels in [Channels::Rgb, Channels::Rgba] {
+        let encoder = Encoder::from_fn(18, 10, channels, &checkerboard)?;
+        let encoded = encoder.encode_to_vec()?;
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+        let (header, decoded) = decode_to_vec(&encoded)?;
+        assert_eq!((header.width, header.height, header.channels), (18, 10, channels));
+        let n = channels.bytes_per_pixel();
+        for (i, px) in decoded.chunks_exact(n).enumerate() {
+            let (x, y) = (i as u32 % 18, i as u32 / 18);
+            assert_eq!(px, &checkerboard(x, y)[..n]);
+        }
+    }
+    assert!(Encoder::from_fn(0, 10, Channels::Rgb, &checkerboard).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_encode_one_op() {
+    let prev = [10, 20, 30, 255];
+    assert_eq!(encode_one_op(prev, prev), [QOI_OP_RUN]);
+    assert_eq!(encode_one_op(prev, [0, 0, 0, 0]), [0x00]); // index
+    assert_eq!(encode_one_op(prev, [11, 19, 30, 255]), [0x76]); // diff: +1, -1, 0
+    assert_eq!(encode_one_op(prev, [25, 30, 32, 255]), [0xaa, 0xd0]); // luma: vg = 10
+    assert_eq!(encode_one_op(prev, [100, 200, 50, 255]), [0xfe, 100, 200, 50]);
+    assert_eq!(encode_one_op(prev, [10, 20, 30, 4]), [0xff, 10, 20, 30, 4]);
+}
+
+#[test]
+fn test_encode_to_stream_with_limit() -> Result<()> {
+    let data = (0..64 * 64 * 3).map(|i| (i * 31 % 251) as u8).collect::<Vec<_>>();
+    let encoder = Encoder::new(&data, 64, 64)?;
+    let encoded = encoder.encode_to_vec()?;
+    let mut out = Vec::new();
+    assert_eq!(encoder.encode_to_stream_with_limit(&mut out, encoded.len())?, encoded.len());
+    assert_eq!(out, encoded);
+
+    let limit = encoded.len() / 2;
+    let mut out = Vec::new();
+    let res = encoder.encode_to_stream_with_limit(&mut out, limit);
+    assert!(
+        matches!(res, Err(Error::SizeBudgetExceeded { size, budget }) if size > limit && budget == limit)
+    );
+    assert!(out.len() <= limit);
+    assert_eq!(out, encoded[..out.len()]);
+
+    let mut out = Vec::new();
+    let res = encoder.encode_to_stream_with_limit(&mut out, 10);
+    assert!(matches!(res, Err(Error::SizeBudgetExceeded { size: 14, budget: 10 })));
+    assert!(out.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_encode_detect_channel_order_hint() -> Result<()> {
+    // warm, mostly orange-ish pixels with some gray ones
+    let rgb: Vec<u8> =
+        (0..100_u8).flat_map(|i| [200 + i % 50, 120 + i % 30, 40 + i % 20]).collect();
+    let rgb = [rgb, vec![128; 30 * 3]].concat();
+    let hint = Encoder::new(&rgb, 13, 10)?.detect_channel_order_hint();
+    assert!(!hint.suggest_swap);
+    assert_eq!((hint.n_red_dominant, hint.n_blue_dominant), (100, 0));
+
+    let bgr: Vec<u8> = rgb.chunks(3).flat_map(|px| [px[2], px[1], px[0]]).collect();
+    let hint = Encoder::new(&bgr, 13, 10)?.detect_channel_order_hint();
+    assert!(hint.suggest_swap);
+    assert_eq!((hint.n_red_dominant, hint.n_blue_dominant), (0, 100));
+    assert!(hint.mean[2] > hint.mean[1] && hint.mean[1] > hint.mean[0]);
+
+    let gray = vec![77; 13 * 10 * 4];
+    let hint = Encoder::new(&gray, 13, 10)?.detect_channel_order_hint();
+    assert_eq!(hint.mean, [77; 3]);
+    assert!(!hint.suggest_swap);
+    Ok(())
+}
+
+#[test]
+fn test_encode_from_rgb565() -> Result<()> {
+    let pixels = [0xffff_u16, 0x0000, 0x8410, 0xf800, 0x07e0, 0x001f];
+    let data: Vec<u8> = pixels.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let encoder = Encoder::from_rgb565(&data, 3, 2)?;
+    assert_eq!(encoder.channels(), Channels::Rgb);
+    let encoded = encoder.encode_to_vec()?;
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+    let (header, decoded) = decode_to_vec(&encoded)?;
+    assert_eq!(header.channels, Channels::Rgb);
+    #[rustfmt::skip]
+    let expected = [
+        255, 255, 255, 0, 0, 0, 132, 130, 132,
+        255, 0, 0, 0, 255, 0, 0, 0, 255,
+    ];
+    assert_eq!(decoded, expected);
+    assert_eq!(qoi::Decoder::new(&encoded)?.decode_to_rgb565_vec()?.1, data);
+
+    // same via the raw layout, with padded rows
+    assert_eq!(RawChannels::Rgb565.bytes_per_pixel(), 2);
+    assert_eq!(RawChannels::Rgb565.channels(), Channels::Rgb);
+    let mut padded = vec![0xee; 2 * 8];
+    padded[..6].copy_from_slice(&data[..6]);
+    padded[8..14].copy_from_slice(&data[6..]);
+    let encoder = Encoder::new_raw(&padded, 3, 2, 8, RawChannels::Rgb565)?;
+    assert_eq!(encoder.encode_to_vec()?, encoded);
+    assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+    let res = Encoder::new_raw(&padded, 3, 2, 5, RawChannels::Rgb565);
+    assert!(matches!(res, Err(Error::InvalidStride { stride: 5, min_stride: 6 })));
+    assert!(matches!(
+        Encoder::from_rgb565(&data[1..], 3, 2),
+        Err(Error::InvalidImageLength { size: 11, width: 3, height: 2 })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_encode_with_compat() -> Result<()> {
+    // a single-pixel run of a color that's in the index, followed by another color
+    let data = [[10, 20, 30, 40], [10, 20, 30, 40], [50, 60, 70, 80]].concat();
+    let hash = 12; // (10 * 3 + 20 * 5 + 30 * 7 + 40 * 11) % 64
+    let rgba = [0xff, 10, 20, 30, 40];
+    let (spec, fast) = (
+        Encoder::new(&data, 3, 1)?.with_compat(CompatMode::Spec),
+        Encoder::new(&data, 3, 1)?.with_compat(CompatMode::Fast),
+    );
+    for (encoder, op) in [(&spec, QOI_OP_RUN), (&fast, hash)] {
+        let encoded = encoder.encode_to_vec()?;
+        assert_eq!(encoded[QOI_HEADER_SIZE..][..6], [&rgba[..], &[op]].concat());
+        assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+        assert_eq!(decode_to_vec(&encoded)?.1, data);
+    }
+    assert_eq!(spec.encode_to_vec()?, encode_canonical(&data, 3, 1)?);
+    let default = if cfg!(feature = "reference") { &spec } else { &fast };
+    assert_eq!(encode_to_vec(&data, 3, 1)?, default.encode_to_vec()?);
+    assert_eq!(CompatMode::default() == CompatMode::Spec, cfg!(feature = "reference"));
+    Ok(())
+}
+
+#[test]
+fn test_encode_to_vec_pooled() -> Result<()> {
+    let pool = Arc::new(BufferPool::new());
+    let handles = (0..8_u8)
+        .map(|i| {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || -> Result<()> {
+                let mut rng = StdRng::seed_from_u64(u64::from(i));
+                for _ in 0..20 {
+                    let (w, h) = (rng.gen_range(1..20), rng.gen_range(1..20));
+                    let data = (0..w * h * 4).map(|_| rng.gen_range(0..4) * 60).collect::<Vec<_>>();
+                    let encoder = Encoder::new(&data, w, h)?;
+                    let encoded = encoder.encode_to_vec_pooled(&pool)?;
+                    assert_eq!(*encoded, encoder.encode_to_vec()?);
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
+    // every buffer went back to the pool, and there are never more than one per thread
+    assert!((1..=8).contains(&pool.len()));
+    let n_idle = pool.len();
+    let encoded = encode_to_vec([1, 2, 3], 1, 1)?;
+    let pooled = Encoder::new(&[1, 2, 3], 1, 1)?.encode_to_vec_pooled(&pool)?;
+    assert_eq!(pool.len(), n_idle - 1);
+    assert_eq!(pooled.as_ref(), &encoded[..]);
+    assert_eq!(pooled.into_vec(), encoded);
+    assert_eq!(pool.len(), n_idle - 1);
+    Ok(())
+}
+
+#[test]
+fn test_theoretical_min_size() -> Result<()> {
+    // a run of one between two index hits: a run op and an index op are a tie
+    let (a, b) = ([10, 20, 30, 40], [200, 100, 50, 40]);
+    let data = [a, b, b, a, a, b].concat();
+    let min_size = theoretical_min_size(&data, 6, 1, 4)?;
+    for compat in [CompatMode::Spec, CompatMode::Fast] {
+        let encoder = Encoder::new(&data, 6, 1)?.with_compat(compat);
+        assert_eq!(encoder.encode_to_vec()?.len(), min_size);
+    }
+    // header + (rgba, rgb, run, index, run, index) + padding
+    assert_eq!(min_size, QOI_HEADER_SIZE + 5 + 4 + 1 + 1 + 1 + 1 + 8);
+    assert_eq!(theoretical_min_size(&data, 3, 2, 4)?, min_size);
+
+    // a leading run of the initial pixel doesn't insert it into the index, while a zero diff
+    // does, so that it can be referenced later on
+    let data = [0, 0, 0, 200, 100, 50, 0, 0, 0];
+    let greedy = encode_to_vec(data, 3, 1)?;
+    let ops = [QOI_OP_DIFF | 0x2a, QOI_OP_RGB, 200, 100, 50, QOI_OP_INDEX | 53];
+    let optimal = [&greedy[..QOI_HEADER_SIZE], &ops, &QOI_PADDING].concat();
+    assert_eq!(decode_to_vec(&optimal)?.1, data);
+    assert_eq!(theoretical_min_size(&data, 3, 1, 3)?, optimal.len());
+    assert_eq!(optimal.len(), greedy.len() - 3);
+    // ...but the zero diff doesn't help if the color doesn't reappear
+    let data = [0, 0, 0, 0, 0, 0, 200, 100, 50];
+    assert_eq!(theoretical_min_size(&data, 3, 1, 3)?, encode_to_vec(data, 3, 1)?.len());
+
+    let res = theoretical_min_size(&data, 2, 1, 3);
+    assert!(matches!(res, Err(Error::InvalidImageLength { size: 9, width: 2, height: 1 })));
+    let res = theoretical_min_size(&data, 3, 1, 2);
+    assert!(matches!(res, Err(Error::InvalidChannels { channels: 2 })));
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for channels in [3, 4] {
+        let data = (0..64 * 64 * channels).map(|_| rng.gen_range(0..3) * 2).collect::<Vec<_>>();
+        let min_size = theoretical_min_size(&data, 64, 64, channels as u8)?;
+        let encoder = || Encoder::new(&data, 64, 64);
+        assert!(encoder()?.encode_to_vec()?.len() >= min_size);
+        assert!(encoder()?.with_disable_index(true).encode_to_vec()?.len() > min_size);
+        assert!(encoder()?.with_raw_ops(true).encode_to_vec()?.len() > min_size);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_single_pixel() -> Result<()> {
+    // same as the initial previous pixel: a single-pixel run flushed right away; a transparent
+    // black pixel hits the zero-initialized index; anything else is a regular op
+    let cases: [(&[u8], &[u8]); 5] = [
+        (&[0, 0, 0], &[QOI_OP_RUN]),
+        (&[1, 2, 3], &[QOI_OP_LUMA | 34, 0x79]),
+        (&[0, 0, 0, 255], &[QOI_OP_RUN]),
+        (&[0, 0, 0, 0], &[QOI_OP_INDEX]),
+        (&[5, 6, 7, 8], &[QOI_OP_RGBA, 5, 6, 7, 8]),
+    ];
+    for (data, ops) in cases {
+        for compat in [CompatMode::Spec, CompatMode::Fast] {
+            let encoder = Encoder::new(&data, 1, 1)?.with_compat(compat);
+            let encoded = encoder.encode_to_vec()?;
+            assert_eq!(encoded[QOI_HEADER_SIZE..], [ops, &QOI_PADDING].concat());
+            assert_eq!(encoder.byte_iter().collect::<Vec<_>>(), encoded);
+            assert_eq!(encoder.encoded_len()?, encoded.len());
+            assert_eq!(decode_to_vec(&encoded)?.1, data);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_working_memory_bytes() -> Result<()> {
+    // the color index dominates: 256 RGBA entries, plus a few bytes of state
+    let rgb = Encoder::new(&[0; 3 * 4], 2, 2)?.working_memory_bytes();
+    let rgba = Encoder::new(&[0; 4 * 4], 2, 2)?.working_memory_bytes();
+    assert!((1024..1024 + 16).contains(&rgb));
+    assert!((1024..1024 + 16).contains(&rgba));
+    assert!(rgb <= rgba);
+    assert_eq!(Encoder::new(&[0; 4 * 1000], 40, 25)?.working_memory_bytes(), rgba);
+    Ok(())
+}
+
+#[test]
+fn test_encode_prewarmed() -> Result<()> {
+    // a palette image whose first occurrences of each color are all encoded by value
+    let palette = [[200, 10, 10, 192], [10, 200, 10, 128], [10, 10, 200, 64], [80, 90, 0, 0]];
+    let mut rng = StdRng::seed_from_u64(0);
+    let row = palette.iter().flat_map(|c| [*c; 4]).collect::<Vec<_>>();
+    let rest = (0..16 * 15).map(|_| palette[rng.gen_range(0..4)]).collect::<Vec<_>>();
+    let data = [row, rest].concat().concat();
+    for (data, channels) in
+        [(data.clone(), 4), (data.chunks(4).flat_map(|p| &p[..3]).copied().collect(), 3)]
+    {
+        let encoder = Encoder::new(&data, 16, 16)?;
+        let prewarmed = encoder.encode_to_vec_prewarmed()?;
+        assert_eq!(prewarmed[..4], *b"qoip");
+        assert_eq!(prewarmed[QOI_HEADER_SIZE], 4);
+        let (header, decoded) = decode_to_vec_prewarmed(&prewarmed)?;
+        assert_eq!((header.width, header.channels.as_u8()), (16, channels));
+        assert_eq!(decoded, data);
+        assert!(decode_to_vec(&prewarmed).is_err());
+        // each seeded color replaces a value op: the count byte is the only overhead
+        let standard = encoder.encode_to_vec()?;
+        assert_eq!(prewarmed.len(), standard.len() + 1);
+        assert!(decode_to_vec_prewarmed(&standard).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_fn_to_stream() -> Result<()> {
+    struct Sink {
+        data: Vec<u8>,
+        n_flushes: usize,
+    }
+    impl std::io::Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.data.write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.n_flushes += 1;
+            Ok(())
+        }
+    }
+
+    let (w, h) = (1000, 700);
+    let f = |x: u32, y: u32| [(x ^ y) as u8, (x * 7 / (y + 1)) as u8, (x + y * 3) as u8, 255];
+    for channels in [Channels::Rgb, Channels::Rgba] {
+        let mut sink = Sink { data: Vec::new(), n_flushes: 0 };
+        let n = encode_fn_to_stream(w, h, channels, f, &mut sink)?;
+        assert_eq!(n, sink.data.len());
+        assert!(n > 2 * STREAM_FLUSH_CHUNK);
+        assert!(sink.n_flushes >= n / STREAM_FLUSH_CHUNK);
+        assert_eq!(sink.data, Encoder::from_fn(w, h, channels, &f)?.encode_to_vec()?);
+
+        let (header, decoded) = decode_to_vec(&sink.data)?;
+        assert_eq!((header.width, header.height, header.channels), (w, h, channels));
+        let expected = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .flat_map(|(x, y)| f(x, y)[..channels.as_u8() as usize].to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encode_alpha_threshold() -> Result<()> {
+    let data = [10, 20, 30, 100, 40, 50, 60, 200, 70, 80, 90, 127, 1, 2, 3, 128];
+    let encoded = Encoder::new(&data, 2, 2)?.with_alpha_threshold(128).encode_to_vec()?;
+    let (_, decoded) = decode_to_vec(&encoded)?;
+    assert_eq!(decoded, [10, 20, 30, 0, 40, 50, 60, 255, 70, 80, 90, 0, 1, 2, 3, 255]);
+
+    // a solid disc with a feathered (anti-aliased) edge
+    let (width, height) = (64_u32, 64_u32);
+    let data = (0..width * height)
+        .flat_map(|i| {
+            let (dx, dy) = ((i % width) as f64 - 31.5, (i / width) as f64 - 31.5);
+            let alpha = ((28. - (dx * dx + dy * dy).sqrt()) * 64.).clamp(0., 255.);
+            [200, 120, 40, alpha as u8]
+        })
+        .collect::<Vec<_>>();
+    let full = encode_to_vec(&data, width, height)?;
+    let encoder = Encoder::new(&data, width, height)?.with_alpha_threshold(128);
+    let snapped = encoder.encode_to_vec()?;
+    assert!(snapped.len() < full.len());
+    let (_, decoded) = decode_to_vec(&snapped)?;
+    for (px, orig) in decoded.chunks(4).zip(data.chunks(4)) {
+        assert_eq!(px[..3], orig[..3]);
+        assert_eq!(px[3], if orig[3] >= 128 { 255 } else { 0 });
+    }
+
+    let rgb = [1, 2, 3, 4, 5, 6];
+    let encoded = Encoder::new(&rgb, 2, 1)?.with_alpha_threshold(128).encode_to_vec()?;
+    assert_eq!(encoded, encode_to_vec(rgb, 2, 1)?);
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Tests that have to pass with either of the `encode` and `decode` features disabled.
+
+#[allow(unused)]
+const ENCODED: [u8; 27] = [
+    b'q', b'o', b'i', b'f', 0, 0, 0, 3, 0, 0, 0, 1, 3, 0, // header
+    0xfe, 100, 150, 200,  // QOI_OP_RGB
+    0xc1, // QOI_OP_RUN (2 pixels)
+    0, 0, 0, 0, 0, 0, 0, 1, // padding
+];
+
+#[allow(unused)]
+const PIXELS: [u8; 9] = [100, 150, 200, 100, 150, 200, 100, 150, 200];
+
+#[cfg(feature = "decode")]
+#[test]
+fn test_decode_only() -> qoi::Result<()> {
+    let mut buf = [0; 9];
+    let header = qoi::decode_to_buf(&mut buf, ENCODED)?;
+    assert_eq!((header.width, header.height, header.channels), (3, 1, qoi::Channels::Rgb));
+    assert_eq!(buf, PIXELS);
+    Ok(())
+}
+
+#[cfg(feature = "encode")]
+#[test]
+fn test_encode_only() -> qoi::Result<()> {
+    let mut buf = [0; 64];
+    let size = qoi::encode_to_buf(&mut buf, PIXELS, 3, 1)?;
+    assert_eq!(buf[..size], ENCODED);
+    Ok(())
+}
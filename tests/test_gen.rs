@@ -1,3 +1,5 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
 mod common;
 
 use bytemuck::cast_slice;
@@ -16,7 +18,7 @@ use qoi::consts::{
     QOI_HEADER_SIZE, QOI_MASK_2, QOI_OP_DIFF, QOI_OP_INDEX, QOI_OP_LUMA, QOI_OP_RGB, QOI_OP_RGBA,
     QOI_OP_RUN, QOI_PADDING_SIZE,
 };
-use qoi::{decode_header, decode_to_vec, encode_to_vec};
+use qoi::{decode_header, decode_to_vec, encode_canonical, encode_to_vec};
 
 use self::common::hash;
 
@@ -38,9 +40,7 @@ impl<const N: usize> GenState<N> {
     }
     pub fn write(&mut self, px: [u8; N]) {
         self.index[hash(px) as usize] = px;
-        for i in 0..N {
-            self.pixels.push(px[i]);
-        }
+        self.pixels.extend_from_slice(&px);
         self.prev = px;
         self.len += 1;
     }
@@ -298,6 +298,11 @@ fn test_generated() {
         let size = (img.len() / channels) as u32;
         let encoded = encode(&img, size).unwrap();
         let encoded_c = encode_c(&img, size).unwrap();
+        let canonical = encode_canonical(&img, size, 1).unwrap();
+        assert!(
+            canonical.as_slice() == encoded_c.as_ref(),
+            "qoi-rust [canonical] doesn't match qoi.h"
+        );
         cfg_if! {
             if #[cfg(feature = "reference")] {
                 let eq = encoded.as_slice() == encoded_c.as_ref();
@@ -0,0 +1,30 @@
+#![cfg(all(feature = "image", feature = "encode"))]
+
+use image::RgbaImage;
+
+use qoi::{decode_into_rgba_image, encode_to_vec, Result};
+
+#[test]
+fn test_decode_into_rgba_image() -> Result<()> {
+    let (width, height) = (13, 9);
+    let frames = (0..3_usize)
+        .map(|k| (0..width * height * 4).map(|i| (i as usize * (k + 1) % 253) as u8).collect())
+        .collect::<Vec<Vec<u8>>>();
+    let mut img = RgbaImage::new(1, 1);
+    let mut ptr = None;
+    for frame in &frames {
+        decode_into_rgba_image(encode_to_vec(frame, width, height)?, &mut img)?;
+        assert_eq!(img.dimensions(), (width, height));
+        assert_eq!(img.as_raw(), frame);
+        // same-sized frames are decoded into the same buffer
+        assert_eq!(*ptr.get_or_insert(img.as_raw().as_ptr()), img.as_raw().as_ptr());
+    }
+
+    // 3-channel images get an opaque alpha channel
+    let rgb = (0..4 * 2 * 3).map(|i| i as u8).collect::<Vec<_>>();
+    decode_into_rgba_image(encode_to_vec(&rgb, 4, 2)?, &mut img)?;
+    assert_eq!(img.dimensions(), (4, 2));
+    let expected = rgb.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 255]);
+    assert_eq!(img.as_raw(), &expected.collect::<Vec<_>>());
+    Ok(())
+}
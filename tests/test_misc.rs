@@ -1,6 +1,276 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
+use qoi::{
+    maybe_convert_channels, swizzle_in_place, try_cast_pixels, Channels, ColorSpace, Component,
+    ComponentOrder, Decoder, Encoder, Endianness, Error, Header, HeaderBuilder, RawChannels,
+    Result,
+};
+
 #[test]
 fn test_new_encoder() {
     // this used to fail due to `Bytes` not being `pub`
     let arr = [0u8];
     let _ = qoi::Decoder::new(&arr[..]);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_header_builder() {
+    let header = Header::builder()
+        .width(640)
+        .height(480)
+        .channels(Channels::Rgba)
+        .colorspace(ColorSpace::Linear)
+        .build()
+        .unwrap();
+    assert_eq!(header, Header::try_new(640, 480, Channels::Rgba, ColorSpace::Linear).unwrap());
+
+    let header = HeaderBuilder::new().width(3).height(2).build().unwrap();
+    assert_eq!(header, Header::try_new(3, 2, Channels::Rgb, ColorSpace::Srgb).unwrap());
+
+    let res = HeaderBuilder::new().width(3).build();
+    assert!(matches!(res, Err(Error::InvalidImageDimensions { width: 3, height: 0 })));
+}
+
+#[test]
+fn test_header_aspect() -> Result<()> {
+    let wide = Header::try_new(640, 480, Channels::Rgb, ColorSpace::Srgb)?;
+    assert!((wide.aspect_ratio() - 4. / 3.).abs() < 1e-6);
+    assert_eq!((wide.is_landscape(), wide.is_portrait(), wide.is_square()), (true, false, false));
+    let tall = Header::try_new(100, 400, Channels::Rgb, ColorSpace::Srgb)?;
+    assert_eq!(tall.aspect_ratio(), 0.25);
+    assert_eq!((tall.is_landscape(), tall.is_portrait(), tall.is_square()), (false, true, false));
+    let square = Header::try_new(7, 7, Channels::Rgba, ColorSpace::Srgb)?;
+    assert_eq!(square.aspect_ratio(), 1.);
+    assert_eq!(
+        (square.is_landscape(), square.is_portrait(), square.is_square()),
+        (false, false, true)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_encoder_decoder_debug() -> Result<()> {
+    let data = [0_u8; 5 * 2 * 4];
+    let encoder = Encoder::new(&data, 5, 2)?.with_colorspace(ColorSpace::Linear);
+    let expected =
+        "Encoder { width: 5, height: 2, channels: Rgba, colorspace: Linear, stride: 20, .. }";
+    assert_eq!(format!("{encoder:?}"), expected);
+    let encoded = encoder.encode_to_vec()?;
+    let decoder = Decoder::new(&encoded)?.with_channels(Channels::Rgb);
+    let expected =
+        "Decoder { width: 5, height: 2, channels: Rgb, colorspace: Linear, stride: 15, .. }";
+    assert_eq!(format!("{decoder:?}"), expected);
+    assert!(format!("{:?}", Decoder::from_stream(&encoded[..])?).contains("channels: Rgba"));
+
+    let strided = [0_u8; 2 * 32 + 16];
+    let encoder = Encoder::new_raw(&strided, 5, 3, 32, Channels::Rgb)?;
+    assert!(format!("{encoder:?}").contains("channels: Rgb, colorspace: Srgb, stride: 32, .."));
+    let encoder = Encoder::new_raw(&strided, 5, 3, 32, RawChannels::Rgb565)?;
+    assert!(format!("{encoder:?}").contains("stride: 32"));
+    let encoder = Encoder::from_rgb565(&strided[..30], 5, 3)?;
+    assert!(format!("{encoder:?}").contains("stride: 10"));
+    let f = |_, _| [0; 4];
+    let encoder = Encoder::from_fn(5, 3, Channels::Rgba, &f)?;
+    assert!(!format!("{encoder:?}").contains("stride"));
+    Ok(())
+}
+
+#[test]
+fn test_error_display_no_alloc() {
+    use core::fmt::Write;
+
+    // formats into a fixed stack buffer, the same way it would be done without `alloc`
+    struct StackBuf([u8; 256], usize);
+
+    impl Write for StackBuf {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let end = self.1 + s.len();
+            self.0.get_mut(self.1..end).ok_or(core::fmt::Error)?.copy_from_slice(s.as_bytes());
+            self.1 = end;
+            Ok(())
+        }
+    }
+
+    let errors = [
+        (Error::InvalidMagic { magic: 0 }, "invalid magic"),
+        (Error::InvalidChannels { channels: 5 }, "invalid number of channels: 5"),
+        (Error::InvalidColorSpace { colorspace: 2 }, "invalid color space: 2"),
+        (Error::InvalidImageDimensions { width: 0, height: 1 }, "invalid image dimensions: 0x1"),
+        (Error::InvalidImageLength { size: 7, width: 1, height: 2 }, "invalid image length: 7"),
+        (Error::InvalidPixelDataLength { size: 5, channels: Channels::Rgb }, "invalid pixel data"),
+        (Error::InvalidStride { stride: 5, min_stride: 6 }, "invalid stride: 5 bytes (min: 6)"),
+        (
+            Error::IndivisiblePixelCount { n_pixels: 10, width: 3 },
+            "pixel count 10 is not a multiple of width 3",
+        ),
+        (Error::RowOutOfBounds { row: 4, height: 4 }, "row 4 out of bounds for image height 4"),
+        (
+            Error::ComponentOrderMismatch { channels: Channels::Rgb, order: ComponentOrder::Argb },
+            "component order Argb doesn't match 3 channels",
+        ),
+        (
+            Error::TrailingBytes { size: 3 },
+            "unexpected 3 trailing bytes after the stream end marker",
+        ),
+        (Error::InvalidPaletteIndex { index: 4, palette_len: 4 }, "invalid palette index: 4"),
+        (Error::OutputBufferTooSmall { size: 1, required: 2 }, "output buffer size too small"),
+        (Error::UnexpectedBufferEnd, "unexpected input buffer end"),
+        (Error::InvalidPadding, "invalid padding"),
+        (Error::RunOverflow, "run exceeds the number of remaining pixels"),
+        (Error::PixelCountMismatch { got: 3, expected: 4 }, "pixel count mismatch: got 3"),
+        (Error::SizeBudgetExceeded { size: 9, budget: 8 }, "encoded size exceeds budget: 9"),
+        (Error::TrailerTooLong { size: 300 }, "trailer metadata too long: 300 bytes (max: 255)"),
+        (
+            Error::IncompatibleImages { first: Header::default(), second: Header::default() },
+            "incompatible images: 1x1 (3 channels) and 1x1",
+        ),
+    ];
+    for (err, expected) in errors {
+        let mut buf = StackBuf([0; 256], 0);
+        write!(buf, "{err}").unwrap();
+        let s = core::str::from_utf8(&buf.0[..buf.1]).unwrap();
+        assert!(s.starts_with(expected), "{s:?} doesn't start with {expected:?}");
+        write!(StackBuf([0; 256], 0), "{err:?}").unwrap();
+    }
+}
+
+#[test]
+fn test_maybe_convert_channels() -> Result<()> {
+    use std::borrow::Cow;
+
+    let rgb = [1, 2, 3, 4, 5, 6];
+    let rgba = [1, 2, 3, 255, 4, 5, 6, 255];
+    let res = maybe_convert_channels(&rgb, Channels::Rgb, Channels::Rgb)?;
+    assert!(matches!(res, Cow::Borrowed(data) if data == rgb));
+    let res = maybe_convert_channels(&rgb, Channels::Rgb, Channels::Rgba)?;
+    assert!(matches!(res, Cow::Owned(ref data) if data == &rgba));
+    let res = maybe_convert_channels(&rgba, Channels::Rgba, Channels::Rgb)?;
+    assert!(matches!(res, Cow::Owned(ref data) if data == &rgb));
+    let res = maybe_convert_channels(&rgb[..5], Channels::Rgb, Channels::Rgb);
+    assert!(matches!(res, Err(Error::InvalidPixelDataLength { size: 5, channels: Channels::Rgb })));
+    Ok(())
+}
+
+#[test]
+fn test_swizzle_in_place() -> Result<()> {
+    use Component::{One, Zero, A, B, G, R};
+
+    let mut data = [1, 2, 3, 4, 5, 6, 7, 8];
+    swizzle_in_place(&mut data, Channels::Rgba, [B, G, R, A])?;
+    assert_eq!(data, [3, 2, 1, 4, 7, 6, 5, 8]);
+    swizzle_in_place(&mut data, Channels::Rgba, [B, G, R, A])?;
+    assert_eq!(data, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+    let mut data = [1, 2, 3, 4, 5, 6];
+    swizzle_in_place(&mut data, Channels::Rgb, [A, Zero, R, One])?;
+    assert_eq!(data, [255, 0, 1, 255, 0, 4]);
+    let res = swizzle_in_place(&mut data[..5], Channels::Rgb, [B, G, R, A]);
+    assert!(matches!(res, Err(Error::InvalidPixelDataLength { size: 5, channels: Channels::Rgb })));
+    Ok(())
+}
+
+#[test]
+fn test_try_cast_pixels() {
+    let data = [1, 2, 3, 4, 5, 6, 7, 8];
+    assert_eq!(try_cast_pixels::<4>(&data).unwrap(), &[[1, 2, 3, 4], [5, 6, 7, 8]]);
+    assert_eq!(try_cast_pixels::<3>(&data[..6]).unwrap(), &[[1, 2, 3], [4, 5, 6]]);
+    assert_eq!(try_cast_pixels::<3>(&data[1..7]).unwrap(), &[[2, 3, 4], [5, 6, 7]]);
+    assert!(try_cast_pixels::<4>(&[]).unwrap().is_empty());
+
+    let res = try_cast_pixels::<3>(&data);
+    assert!(matches!(res, Err(Error::InvalidPixelDataLength { size: 8, channels: Channels::Rgb })));
+    let res = try_cast_pixels::<4>(&data[..7]);
+    assert!(matches!(
+        res,
+        Err(Error::InvalidPixelDataLength { size: 7, channels: Channels::Rgba })
+    ));
+    assert!(matches!(try_cast_pixels::<2>(&data), Err(Error::InvalidChannels { channels: 2 })));
+}
+
+#[test]
+fn test_header_endianness() -> Result<()> {
+    let (width, height) = (3, 300);
+    let data = [1, 2, 3, 4].repeat(width as usize * height as usize);
+    let standard = Encoder::new(&data, width, height)?.encode_to_vec()?;
+    let encoder = Encoder::new(&data, width, height)?.with_header_endianness(Endianness::Big);
+    assert_eq!(encoder.encode_to_vec()?, standard);
+
+    let encoder = Encoder::new(&data, width, height)?.with_header_endianness(Endianness::Little);
+    let encoded = encoder.encode_to_vec()?;
+    assert_eq!(encoded[4..12], [3, 0, 0, 0, 44, 1, 0, 0]);
+    assert_eq!(encoded[12..], standard[12..]);
+    assert!(Decoder::new(&encoded).is_err()); // 3 << 24 by 300 << 16 is too large
+    let mut decoder = Decoder::new_with_header_endianness(&encoded, Endianness::Little)?;
+    assert_eq!((decoder.header().width, decoder.header().height), (width, height));
+    assert_eq!(decoder.decode_to_vec()?, data);
+    let decoder = Decoder::from_stream_with_header_endianness(&encoded[..], Endianness::Little)?;
+    assert_eq!(decoder.header(), Decoder::new(&standard)?.header());
+    Ok(())
+}
+
+#[test]
+fn test_encode_decode_file() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("qoi-test-{}.qoi", std::process::id()));
+    let data = (0..5 * 3 * 4).map(|i| (i * 11) as u8).collect::<Vec<_>>();
+    qoi::encode_file(&path, &data, 5, 3)?;
+    let (header, decoded) = qoi::decode_file(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!((header.width, header.height, header.channels), (5, 3, Channels::Rgba));
+    assert_eq!(decoded, data);
+    assert!(matches!(qoi::decode_file(&path), Err(Error::IoError(_))));
+    Ok(())
+}
+
+#[test]
+fn test_channels_accessors() {
+    assert_eq!((Channels::Rgb.count(), Channels::Rgb.bytes_per_pixel()), (3, 3));
+    assert_eq!((Channels::Rgba.count(), Channels::Rgba.bytes_per_pixel()), (4, 4));
+}
+
+#[test]
+fn test_index_slot() {
+    for (rgba, slot) in [
+        ([0, 0, 0, 0], 0),
+        ([0, 0, 0, 255], 53),
+        ([1, 2, 3, 4], 14),
+        ([255, 255, 255, 255], 38),
+        ([10, 200, 30, 128], 24),
+    ] {
+        assert_eq!(qoi::index_slot(rgba), slot, "{rgba:?}");
+        let [r, g, b, a] = rgba.map(u32::from);
+        assert_eq!(u32::from(qoi::index_slot(rgba)), (r * 3 + g * 5 + b * 7 + a * 11) % 64);
+    }
+}
+
+#[test]
+fn test_raw_channels_with_order() -> Result<()> {
+    use ComponentOrder::{Abgr, Argb, Bgr, Bgra, Rgb, Rgba};
+
+    for (order, channels) in [(Rgb, 3), (Bgr, 3), (Rgba, 4), (Bgra, 4), (Argb, 4), (Abgr, 4)] {
+        let channels = Channels::try_from(channels)?;
+        let raw = RawChannels::with_order(channels, order)?;
+        assert_eq!((raw.channels(), raw.order()), (channels, order));
+        let other = if channels == Channels::Rgb { Channels::Rgba } else { Channels::Rgb };
+        assert!(matches!(
+            RawChannels::with_order(other, order),
+            Err(Error::ComponentOrderMismatch { channels, order: o }) if channels == other && o == order
+        ));
+    }
+
+    let raw = RawChannels::try_from((Channels::Rgba, Bgra))?;
+    assert_eq!(raw, RawChannels::with_order(Channels::Rgba, Bgra)?);
+    let res = RawChannels::try_from((Channels::Rgb, Argb));
+    assert!(matches!(res, Err(Error::ComponentOrderMismatch { .. })));
+    assert_eq!(RawChannels::from(Channels::Rgb), RawChannels::with_order(Channels::Rgb, Rgb)?);
+    assert_eq!(RawChannels::from(Channels::Rgba), RawChannels::with_order(Channels::Rgba, Rgba)?);
+
+    let mut data = [1, 2, 3, 4, 5, 6, 7, 8];
+    let raw = RawChannels::with_order(Channels::Rgba, Argb)?;
+    swizzle_in_place(&mut data, raw.channels(), raw.pattern())?;
+    assert_eq!(data, [4, 1, 2, 3, 8, 5, 6, 7]);
+    let mut data = [1, 2, 3];
+    let raw = RawChannels::with_order(Channels::Rgb, Bgr)?;
+    swizzle_in_place(&mut data, raw.channels(), raw.pattern())?;
+    assert_eq!(data, [3, 2, 1]);
+    Ok(())
+}
@@ -0,0 +1,15 @@
+#![cfg(all(feature = "memmap2", feature = "encode"))]
+
+use qoi::{decode_file, decode_mmap, encode_file, Error, Result};
+
+#[test]
+fn test_decode_mmap() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("qoi-test-mmap-{}.qoi", std::process::id()));
+    let data = (0..17 * 9 * 3).map(|i| (i * 7 / 3) as u8).collect::<Vec<_>>();
+    encode_file(&path, &data, 17, 9)?;
+    let (header, decoded) = decode_mmap(&path)?;
+    assert_eq!((header, &decoded), (decode_file(&path)?.0, &data));
+    std::fs::remove_file(&path)?;
+    assert!(matches!(decode_mmap(&path), Err(Error::IoError(_))));
+    Ok(())
+}
@@ -0,0 +1,25 @@
+#![cfg(feature = "ndarray")]
+
+use ndarray::{s, Array3};
+
+use qoi::{decode_to_ndarray, decode_to_vec, encode_from_ndarray, ColorSpace, Error, Result};
+
+#[test]
+fn test_ndarray_roundtrip() -> Result<()> {
+    let array = Array3::from_shape_fn((3, 5, 4), |(y, x, c)| (y * 50 + x * 10 + c) as u8);
+    let encoded = encode_from_ndarray(&array.view(), ColorSpace::Linear)?;
+    let (header, decoded) = decode_to_ndarray(&encoded)?;
+    assert_eq!((header.width, header.height, header.colorspace), (5, 3, ColorSpace::Linear));
+    assert_eq!(decoded, array);
+    assert_eq!(decode_to_vec(&encoded)?.1, array.iter().copied().collect::<Vec<_>>());
+
+    // non-standard layout gets copied before encoding
+    let transposed = array.slice(s![.., ..;-1, ..]);
+    let encoded = encode_from_ndarray(&transposed, ColorSpace::Srgb)?;
+    assert_eq!(decode_to_ndarray(&encoded)?.1, transposed);
+
+    let array = Array3::<u8>::zeros((2, 2, 2));
+    let res = encode_from_ndarray(&array.view(), ColorSpace::Srgb);
+    assert!(matches!(res, Err(Error::InvalidChannels { channels: 2 })));
+    Ok(())
+}
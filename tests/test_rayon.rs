@@ -0,0 +1,57 @@
+#![cfg(all(feature = "rayon", feature = "encode"))]
+
+use qoi::{
+    decode_and_par_map, decode_archive_parallel, decode_banded, decode_banded_parallel,
+    decode_to_vec, encode_to_vec, reencode_banded, Encoder, Error, Result,
+};
+
+#[test]
+fn test_decode_and_par_map() -> Result<()> {
+    let (width, height) = (97, 41);
+    let data = (0..width * height * 4).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let encoded = encode_to_vec(&data, width, height)?;
+    assert_eq!(decode_and_par_map(&encoded, |_| ())?, decode_to_vec(&encoded)?);
+
+    let (_, inverted) =
+        decode_and_par_map(&encoded, |px| px[..3].iter_mut().for_each(|c| *c = !*c))?;
+    let expected = data.chunks_exact(4).flat_map(|px| [!px[0], !px[1], !px[2], px[3]]);
+    assert_eq!(inverted, expected.collect::<Vec<_>>());
+    Ok(())
+}
+
+#[test]
+fn test_decode_archive_parallel() -> Result<()> {
+    let mut archive = Vec::new();
+    let mut offsets = Vec::new();
+    for (i, (width, height, channels)) in
+        [(13, 7, 3), (1, 1, 4), (64, 33, 4)].into_iter().enumerate()
+    {
+        let data = (0..width * height * channels).map(|j| (j * (i + 3) % 253) as u8);
+        let data = data.collect::<Vec<_>>();
+        let (encoded, layout) =
+            Encoder::new(&data, width as _, height as _)?.encode_to_vec_with_layout()?;
+        offsets.push(archive.len()..archive.len() + layout.total_len);
+        archive.extend(encoded);
+    }
+    let decoded = decode_archive_parallel(&archive, &offsets)?;
+    let sequential = offsets.iter().map(|r| decode_to_vec(&archive[r.clone()]));
+    assert_eq!(decoded, sequential.collect::<Result<Vec<_>>>()?);
+
+    offsets.push(archive.len()..archive.len() + 1);
+    let res = decode_archive_parallel(&archive, &offsets);
+    assert!(matches!(res, Err(Error::UnexpectedBufferEnd)));
+    Ok(())
+}
+
+#[test]
+fn test_decode_banded_parallel() -> Result<()> {
+    let (width, height) = (31, 57);
+    let data = (0..width * height * 4).map(|i| (i % 241) as u8).collect::<Vec<_>>();
+    let banded = reencode_banded(&encode_to_vec(&data, width, height)?, 8)?;
+    let (header, decoded) = decode_banded_parallel(&banded)?;
+    assert_eq!((header.width, header.height), (width, height));
+    assert_eq!(decoded, data);
+    assert_eq!(decode_banded(&banded)?, (header, decoded));
+    assert!(decode_banded_parallel(&banded[..100]).is_err());
+    Ok(())
+}
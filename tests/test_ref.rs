@@ -1,3 +1,5 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 
@@ -0,0 +1,169 @@
+#![cfg(all(feature = "encode", feature = "decode"))]
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use qoi::{
+    average_color, count_unique_colors, count_unique_colors_capped, decode_opaque_bounds,
+    decode_phash, diff_images, encode_to_vec, entropy_estimate, psnr, ssim, Channels, Encoder,
+    Error, Result,
+};
+
+#[test]
+fn test_average_color() -> Result<()> {
+    let (a, b) = ([10, 20, 30, 255], [50, 60, 70, 255]);
+    let encoded = encode_to_vec([a, a, a, b].concat(), 2, 2)?;
+    assert_eq!(average_color(&encoded)?, [20, 30, 40, 255]);
+
+    let encoded = encode_to_vec([[0, 0, 0], [255, 255, 255]].concat(), 2, 1)?;
+    assert_eq!(average_color(&encoded)?, [128, 128, 128, 255]);
+
+    let data = [[0, 100, 200, 0], [0, 100, 200, 100]].repeat(50).concat();
+    let encoded = encode_to_vec(&data, 10, 10)?;
+    assert_eq!(average_color(&encoded)?, [0, 100, 200, 50]);
+    Ok(())
+}
+
+#[test]
+fn test_entropy_estimate() -> Result<()> {
+    let solid = encode_to_vec([1, 2, 3].repeat(64 * 64), 64, 64)?;
+    let mut rng = StdRng::seed_from_u64(0);
+    let random = (0..64 * 64 * 3).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+    let random = encode_to_vec(random, 64, 64)?;
+    let (solid, random) = (entropy_estimate(&solid)?, entropy_estimate(&random)?);
+    assert!((0. ..1.5).contains(&solid), "{solid}");
+    assert!((6. ..=8.).contains(&random), "{random}");
+    Ok(())
+}
+
+#[test]
+fn test_count_unique_colors() -> Result<()> {
+    let (a, b, c) = ([10, 20, 30], [50, 60, 70], [0, 0, 0]);
+    let encoded = encode_to_vec([a, b, a, a, c, b, c, c, a].concat(), 3, 3)?;
+    assert_eq!(count_unique_colors(&encoded)?, 3);
+    assert_eq!(count_unique_colors_capped(&encoded, 3)?, 3);
+    assert_eq!(count_unique_colors_capped(&encoded, 2)?, 3);
+    assert_eq!(count_unique_colors_capped(&encoded, 0)?, 1);
+    Ok(())
+}
+
+#[test]
+fn test_count_unique_colors_capped() -> Result<()> {
+    let data = (0..=255_u8).flat_map(|i| [i, i, 0, i]).collect::<Vec<_>>();
+    let encoded = encode_to_vec(&data, 16, 16)?;
+    assert_eq!(count_unique_colors(&encoded)?, 256);
+    assert_eq!(count_unique_colors_capped(&encoded, 256)?, 256);
+    assert_eq!(count_unique_colors_capped(&encoded, 10)?, 11);
+
+    // the walk stops early, so a truncated tail is never reached
+    let truncated = &encoded[..encoded.len() / 2];
+    assert!(count_unique_colors(truncated).is_err());
+    assert_eq!(count_unique_colors_capped(truncated, 10)?, 11);
+    Ok(())
+}
+
+#[test]
+fn test_diff_images() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (w, h) = (9, 7);
+    let mut data: Vec<u8> = (0..w * h * 4).map(|_| rng.gen::<u8>() & 0xf0).collect();
+    let a = encode_to_vec(&data, w, h)?;
+    assert_eq!(diff_images(&a, &a)?, None);
+    // same pixels encoded differently
+    let raw = Encoder::new(&data, w, h)?.with_raw_ops(true).encode_to_vec()?;
+    assert_ne!(raw, a);
+    assert_eq!(diff_images(&a, &raw)?, None);
+
+    let (old, new) = ([data[80], data[81], data[82], data[83]], [1, 2, 3, 4]);
+    data[80..84].copy_from_slice(&new);
+    let b = encode_to_vec(&data, w, h)?;
+    assert_eq!(diff_images(&a, &b)?, Some((20, old, new)));
+    assert_eq!(diff_images(&b, &a)?, Some((20, new, old)));
+
+    let rgb = encode_to_vec(&data[..w as usize * h as usize * 3], w, h)?;
+    assert!(matches!(diff_images(&a, &rgb), Err(Error::IncompatibleImages { .. })));
+    let wide = encode_to_vec(&data, h, w)?;
+    assert!(matches!(diff_images(&a, &wide), Err(Error::IncompatibleImages { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_decode_phash() -> Result<()> {
+    let gen = |w: u32, h: u32, noise: u8| {
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..h)
+            .flat_map(|y| (0..w).map(move |x| ((x * 7 + y * 3) % 256) as u8))
+            .flat_map(|v| {
+                let v = v.saturating_add(rng.gen_range(0..=noise));
+                [v, v / 2, 255 - v]
+            })
+            .collect::<Vec<_>>()
+    };
+    let hash = decode_phash(&encode_to_vec(gen(40, 30, 0), 40, 30)?)?;
+    assert_eq!(hash, decode_phash(&encode_to_vec(gen(40, 30, 0), 40, 30)?)?);
+    let noisy = decode_phash(&encode_to_vec(gen(40, 30, 6), 40, 30)?)?;
+    assert!((hash ^ noisy).count_ones() <= 4);
+    let inverted = gen(40, 30, 0).iter().map(|v| 255 - v).collect::<Vec<_>>();
+    let inverted = decode_phash(&encode_to_vec(inverted, 40, 30)?)?;
+    assert!((hash ^ inverted).count_ones() >= 48);
+
+    // uniform images have no cells brighter than the mean; tiny images are upscaled
+    assert_eq!(decode_phash(&encode_to_vec([9; 3 * 20 * 20], 20, 20)?)?, 0);
+    let tiny = encode_to_vec([0, 0, 0, 255, 255, 255], 2, 1)?;
+    assert_eq!(decode_phash(&tiny)?, 0xf0f0_f0f0_f0f0_f0f0);
+    assert!(decode_phash(&tiny[..20]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_decode_opaque_bounds() -> Result<()> {
+    // a transparent border around a 3x2 sprite at (2, 1) in a 7x5 image
+    let (width, height) = (7, 5);
+    let mut data = vec![0; width * height * 4];
+    for y in 1..3 {
+        for x in 2..5 {
+            data[(y * width + x) * 4..][..4].copy_from_slice(&[200, 100, 50, 255]);
+        }
+    }
+    let encoded = encode_to_vec(&data, width as u32, height as u32)?;
+    assert_eq!(decode_opaque_bounds(&encoded)?, Some((2, 1, 4, 2)));
+
+    // a single semi-transparent pixel, a fully transparent image, and RGB
+    data[(4 * width + 6) * 4 + 3] = 1;
+    let encoded = encode_to_vec(&data, width as u32, height as u32)?;
+    assert_eq!(decode_opaque_bounds(&encoded)?, Some((2, 1, 6, 4)));
+    let encoded = encode_to_vec(vec![0; 4 * 6], 3, 2)?;
+    assert_eq!(decode_opaque_bounds(&encoded)?, None);
+    let encoded = encode_to_vec(vec![0; 3 * 6], 3, 2)?;
+    assert_eq!(decode_opaque_bounds(&encoded)?, Some((0, 0, 2, 1)));
+
+    // a run that wraps around a row spans the full width
+    let mut data = vec![0; 4 * 4 * 3];
+    data[3 * 4..5 * 4].copy_from_slice(&[9, 9, 9, 9, 9, 9, 9, 9]);
+    let encoded = encode_to_vec(&data, 4, 3)?;
+    assert_eq!(decode_opaque_bounds(&encoded)?, Some((0, 0, 3, 1)));
+    Ok(())
+}
+
+#[test]
+fn test_psnr_ssim() {
+    let original = (0..32 * 32 * 4).map(|i| (i * 7 % 240) as u8).collect::<Vec<_>>();
+    for channels in [Channels::Rgb, Channels::Rgba] {
+        let original = &original[..32 * 32 * channels.bytes_per_pixel()];
+        assert_eq!(psnr(original, original, channels), f64::INFINITY);
+        assert_eq!(psnr(&[], &[], channels), f64::INFINITY);
+        assert!((ssim(original, original, channels) - 1.).abs() < 1e-12);
+        assert_eq!(ssim(&[], &[], channels), 1.);
+
+        // every component off by 5, so the MSE is 25
+        let offset = original.iter().map(|&c| c + 5).collect::<Vec<_>>();
+        let expected = 20. * (255_f64 / 5.).log10();
+        assert!((psnr(original, &offset, channels) - expected).abs() < 1e-9);
+        let similar = ssim(original, &offset, channels);
+        assert!((0.99..1.).contains(&similar), "{similar}");
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let noise = original.iter().map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        assert!(psnr(original, &noise, channels) < 10.);
+        assert!(ssim(original, &noise, channels) < 0.1);
+    }
+}
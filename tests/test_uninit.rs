@@ -0,0 +1,27 @@
+#![cfg(all(feature = "uninit", feature = "encode"))]
+
+use std::mem::MaybeUninit;
+
+use qoi::{decode_to_vec, encode_to_vec, Channels, Decoder, Error, Result};
+
+#[test]
+fn test_decode_to_uninit() -> Result<()> {
+    let data = (0..13 * 7 * 4).map(|i| (i * 5 / 7) as u8).collect::<Vec<_>>();
+    let encoded = encode_to_vec(&data, 13, 7)?;
+    let expected = decode_to_vec(&encoded)?.1;
+
+    let mut buf = vec![MaybeUninit::uninit(); data.len() + 10];
+    let decoded = Decoder::new(&encoded)?.decode_to_uninit(&mut buf)?;
+    assert_eq!(decoded, &expected[..]);
+
+    let mut buf = Vec::with_capacity(13 * 7 * 3);
+    let mut decoder = Decoder::new(&encoded)?.with_channels(Channels::Rgb);
+    let decoded = decoder.decode_to_uninit(buf.spare_capacity_mut())?;
+    let rgb = expected.chunks_exact(4).flat_map(|px| px[..3].to_vec()).collect::<Vec<_>>();
+    assert_eq!(decoded, &rgb[..]);
+
+    let mut buf = vec![MaybeUninit::uninit(); data.len() - 1];
+    let res = Decoder::new(&encoded)?.decode_to_uninit(&mut buf);
+    assert!(matches!(res, Err(Error::OutputBufferTooSmall { .. })));
+    Ok(())
+}